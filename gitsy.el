@@ -11,13 +11,16 @@
 ;;; Commentary:
 
 ;; Gitsy provides a magit-style interface for managing git worktrees.
+;; All git work is done by the `gitsy' binary, which this package drives
+;; through its `--json' mode so that the TUI and Emacs behave identically.
+;; Set `gitsy-executable' if the binary is not on your PATH.
 ;;
 ;; Main entry point: M-x gitsy-status
 ;;
 ;; Features:
 ;; - Create worktrees with new branches (from HEAD, default base, or remote)
 ;; - Delete worktrees with sync-status warnings
-;; - Configuration via .gitsy.toml or customize
+;; - Configuration via .gitsy.toml, shared with the gitsy TUI
 ;; - First-run setup wizard
 ;; - Projectile integration: opens worktrees as projects
 ;;
@@ -47,17 +50,22 @@
   :group 'tools
   :prefix "gitsy-")
 
+(defcustom gitsy-executable "gitsy"
+  "Name or path of the gitsy binary."
+  :type 'string
+  :group 'gitsy)
+
 (defcustom gitsy-worktree-path nil
-  "Default path for worktrees.
-Can be overridden per-repository via .gitsy.toml."
-  :type '(choice (const :tag "Use .gitsy.toml" nil)
+  "Worktree path suggested by the setup wizard.
+The value actually used always comes from gitsy's config files."
+  :type '(choice (const :tag "No suggestion" nil)
                  (directory :tag "Directory"))
   :group 'gitsy)
 
 (defcustom gitsy-default-base-branch nil
-  "Default base branch for new worktrees.
-Can be overridden per-repository via .gitsy.toml."
-  :type '(choice (const :tag "Use .gitsy.toml" nil)
+  "Default base branch suggested by the setup wizard.
+The value actually used always comes from gitsy's config files."
+  :type '(choice (const :tag "No suggestion" nil)
                  (string :tag "Branch name"))
   :group 'gitsy)
 
@@ -126,12 +134,18 @@ When non-nil, newly created worktrees are opened as projectile projects."
   bare-p        ; Non-nil if this is the bare/main repo
   locked-p      ; Non-nil if worktree is locked
   prunable-p    ; Non-nil if worktree can be pruned
-  sync-status)  ; 'synced, 'ahead, 'behind, 'diverged, or 'no-upstream
+  sync-status   ; 'synced, 'ahead, 'behind, 'diverged, 'no-upstream,
+                ; 'upstream-gone, or nil when unknown
+  ahead         ; Commits not on the upstream
+  behind        ; Upstream commits not on the branch
+  merge-status) ; 'merged, 'squash-merged, 'not-merged, or nil without a
+                ; default base branch
 
 (cl-defstruct (gitsy-config (:constructor gitsy-config-create))
   "Configuration for gitsy."
   worktree-path        ; Directory where worktrees are created
-  default-base-branch) ; Default branch for new worktrees (e.g., "origin/main")
+  default-base-branch  ; Default branch for new worktrees (e.g., "origin/main")
+  delete-mode)         ; "worktree", "local-branch" or "remote-branch"
 
 ;;; Buffer-local Variables
 
@@ -144,99 +158,73 @@ When non-nil, newly created worktrees are opened as projectile projects."
 (defvar-local gitsy--worktrees nil
   "List of gitsy-worktree structs for current repository.")
 
-;;; Git Interaction Functions
+;;; Binary Interaction
 
-(defun gitsy--call-git (&rest args)
-  "Call git with ARGS and return output as string.
-Returns nil if git command fails."
+(defun gitsy--call (&rest args)
+  "Run the gitsy binary in JSON mode with ARGS and return the parsed result.
+Objects are returned as alists and arrays as lists.  Nil ARGS are
+dropped so optional flags can be passed inline.  Signals an error
+with gitsy's message if the command fails."
   (with-temp-buffer
-    (let ((exit-code (apply #'call-process "git" nil t nil args)))
-      (if (zerop exit-code)
-          (string-trim (buffer-string))
-        nil))))
+    (let ((exit-code (apply #'call-process gitsy-executable nil '(t nil) nil
+                            "--json" (delq nil args))))
+      (goto-char (point-min))
+      (let ((result (unless (eobp)
+                      (json-parse-buffer :object-type 'alist
+                                         :array-type 'list
+                                         :null-object nil
+                                         :false-object nil))))
+        (unless (zerop exit-code)
+          (error "Gitsy: %s" (or (alist-get 'error result)
+                                 (format "exited with status %s" exit-code))))
+        result))))
 
 (defun gitsy--call-git-lines (&rest args)
-  "Call git with ARGS and return output as list of lines."
-  (when-let ((output (apply #'gitsy--call-git args)))
-    (split-string output "\n" t)))
+  "Call git with ARGS and return output as list of lines.
+Returns nil if the git command fails."
+  (with-temp-buffer
+    (when (zerop (apply #'call-process "git" nil t nil args))
+      (split-string (buffer-string) "\n" t))))
 
 (defun gitsy--find-repo-root ()
   "Find the root of the git repository.
 Returns the path to the working directory."
   (let ((default-directory (or default-directory ".")))
-    (gitsy--call-git "rev-parse" "--show-toplevel")))
+    (car (gitsy--call-git-lines "rev-parse" "--show-toplevel"))))
 
 (defun gitsy--list-worktrees ()
-  "Return list of gitsy-worktree structs.
-Parses output of: git worktree list --porcelain"
-  (let ((output (gitsy--call-git "worktree" "list" "--porcelain"))
-        (worktrees nil)
-        (current-wt nil))
-    (when output
-      (dolist (line (split-string output "\n"))
-        (cond
-         ((string-prefix-p "worktree " line)
-          (when current-wt
-            (push current-wt worktrees))
-          (setq current-wt (gitsy-worktree-create
-                            :path (substring line 9))))
-         ((string-prefix-p "HEAD " line)
-          (when current-wt
-            (setf (gitsy-worktree-head current-wt)
-                  (substring line 5 12)))) ; short sha
-         ((string-prefix-p "branch " line)
-          (when current-wt
-            (let ((branch (substring line 7)))
-              (setf (gitsy-worktree-branch current-wt)
-                    (if (string-prefix-p "refs/heads/" branch)
-                        (substring branch 11)
-                      branch)))))
-         ((string= "bare" line)
-          (when current-wt
-            (setf (gitsy-worktree-bare-p current-wt) t)))
-         ((string= "locked" line)
-          (when current-wt
-            (setf (gitsy-worktree-locked-p current-wt) t)))
-         ((string= "prunable" line)
-          (when current-wt
-            (setf (gitsy-worktree-prunable-p current-wt) t)))))
-      (when current-wt
-        (push current-wt worktrees)))
-    ;; Add sync status to each worktree
-    (dolist (wt worktrees)
-      (when (gitsy-worktree-branch wt)
-        (setf (gitsy-worktree-sync-status wt)
-              (gitsy--branch-sync-status (gitsy-worktree-branch wt)))))
-    (nreverse worktrees)))
-
-(defun gitsy--branch-sync-status (branch)
-  "Check if BRANCH is in sync with its upstream.
-Returns: \\='synced, \\='ahead, \\='behind, \\='diverged, or \\='no-upstream"
-  (let ((output (gitsy--call-git "rev-list" "--left-right" "--count"
-                                  (format "%s...%s@{upstream}" branch branch))))
-    (if (null output)
-        'no-upstream
-      (let ((counts (split-string output)))
-        (if (= (length counts) 2)
-            (let ((ahead (string-to-number (nth 0 counts)))
-                  (behind (string-to-number (nth 1 counts))))
-              (cond
-               ((and (zerop ahead) (zerop behind)) 'synced)
-               ((and (> ahead 0) (> behind 0)) 'diverged)
-               ((> ahead 0) 'ahead)
-               ((> behind 0) 'behind)
-               (t 'synced)))
-          'no-upstream)))))
-
-(defun gitsy--sync-status-string (status)
-  "Return display string for sync STATUS."
-  (pcase status
-    ('synced "synced")
-    ('ahead "ahead")
-    ('behind "behind")
-    ('diverged "diverged")
-    ('no-upstream "no upstream")
-    (_ "unknown")))
+  "Return list of gitsy-worktree structs for the managed worktrees."
+  (mapcar #'gitsy--worktree-from-json (gitsy--call "list")))
+
+(defun gitsy--worktree-from-json (entry)
+  "Build a gitsy-worktree from ENTRY, one object of `gitsy --json list'."
+  (let ((head (alist-get 'head entry)))
+    (gitsy-worktree-create
+     :path (alist-get 'path entry)
+     :branch (alist-get 'branch entry)
+     :head (and head (substring head 0 7))
+     :bare-p (alist-get 'bare entry)
+     :locked-p (alist-get 'locked entry)
+     :prunable-p (alist-get 'prunable entry)
+     :sync-status (when-let ((state (alist-get 'sync_state entry)))
+                    (intern (string-replace "_" "-" state)))
+     :ahead (alist-get 'ahead entry)
+     :behind (alist-get 'behind entry)
+     :merge-status (when-let ((state (alist-get 'merge_state entry)))
+                     (intern (string-replace "_" "-" state))))))
+
+(defun gitsy--sync-status-string (worktree)
+  "Return display string for the sync status of WORKTREE."
+  (let ((ahead (gitsy-worktree-ahead worktree))
+        (behind (gitsy-worktree-behind worktree)))
+    (pcase (gitsy-worktree-sync-status worktree)
+      ('synced "synced")
+      ('ahead (format "%d ahead" ahead))
+      ('behind (format "%d behind" behind))
+      ('diverged (format "diverged %d/%d" ahead behind))
+      ('no-upstream "no upstream")
+      ('upstream-gone "upstream gone")
+      (_ "unknown"))))
 
 (defun gitsy--sync-status-face (status)
   "Return face for sync STATUS."
@@ -246,145 +234,119 @@ Returns: \\='synced, \\='ahead, \\='behind, \\='diverged, or \\='no-upstream"
     ('behind 'gitsy-sync-warning-face)
     ('diverged 'gitsy-sync-error-face)
     ('no-upstream 'gitsy-sync-error-face)
+    ('upstream-gone 'gitsy-sync-error-face)
     (_ 'default)))
 
+(defun gitsy--sync-at-risk-p (status)
+  "Return non-nil if deleting a branch with sync STATUS could lose commits."
+  (not (memq status '(synced behind))))
+
 (defun gitsy--list-remotes ()
   "Return list of remote names."
-  (gitsy--call-git-lines "remote"))
+  (gitsy--call "remotes"))
 
 (defun gitsy--fetch-remote (remote)
-  "Fetch from REMOTE with prune."
+  "Fetch from REMOTE with prune and return its remote-tracking branches."
   (message "Fetching from %s..." remote)
-  (let ((result (gitsy--call-git "fetch" remote "--prune")))
-    (if result
-        (message "Fetched from %s" remote)
-      (message "Fetch from %s completed" remote))
-    t))
-
-(defun gitsy--list-remote-branches (remote)
-  "Return list of branches for REMOTE."
-  (let ((branches (gitsy--call-git-lines "branch" "-r" "--format=%(refname:short)")))
-    (seq-filter (lambda (b)
-                  (and (string-prefix-p (concat remote "/") b)
-                       (not (string-match-p "HEAD" b))))
-                branches)))
+  (let ((branches (alist-get 'branches (gitsy--call "fetch" remote))))
+    (message "Fetched from %s" remote)
+    branches))
 
 (defun gitsy--list-local-branches ()
   "Return list of local branch names."
   (gitsy--call-git-lines "branch" "--format=%(refname:short)"))
 
 (defun gitsy--create-worktree (branch-name &optional base-branch)
-  "Create a new worktree for BRANCH-NAME.
-If BASE-BRANCH is provided, use it as the starting point."
-  (let* ((worktree-dir (gitsy--resolve-worktree-path))
-         (branch-path (expand-file-name branch-name worktree-dir)))
-    ;; Ensure worktree directory exists
-    (unless (file-directory-p worktree-dir)
-      (make-directory worktree-dir t))
-    (let ((result (if base-branch
-                      (gitsy--call-git "worktree" "add" "-b" branch-name
-                                        branch-path base-branch)
-                    (gitsy--call-git "worktree" "add" "-b" branch-name
-                                      branch-path))))
-      (if result
-          (message "Created worktree for branch '%s'" branch-name)
-        ;; Try to get error message
-        (with-temp-buffer
-          (call-process "git" nil t nil "worktree" "add" "-b" branch-name
-                        branch-path (or base-branch "HEAD"))
-          (error "Failed to create worktree: %s" (buffer-string)))))))
-
-(defun gitsy--delete-worktree (worktree &optional force)
-  "Delete WORKTREE and its associated branch.
-If FORCE is non-nil, use --force flag for worktree removal
-and -D for branch deletion."
-  (let ((path (gitsy-worktree-path worktree))
-        (branch (gitsy-worktree-branch worktree)))
-    ;; First remove the worktree
-    (with-temp-buffer
-      (let ((exit-code (if force
-                           (call-process "git" nil t nil "worktree" "remove" "--force" path)
-                         (call-process "git" nil t nil "worktree" "remove" path))))
-        (unless (zerop exit-code)
-          (error "Failed to remove worktree: %s" (buffer-string)))))
-    (message "Removed worktree at '%s'" path)
-    ;; Then delete the branch if it exists
-    (when branch
-      (with-temp-buffer
-        (let ((exit-code (if force
-                             (call-process "git" nil t nil "branch" "-D" branch)
-                           (call-process "git" nil t nil "branch" "-d" branch))))
-          (if (zerop exit-code)
-              (message "Deleted branch '%s'" branch)
-            ;; Branch deletion failed, but worktree is gone - just warn
-            (message "Warning: Could not delete branch '%s': %s"
-                     branch (string-trim (buffer-string)))))))))
-
-(defun gitsy--resolve-worktree-path ()
-  "Resolve the worktree path from config."
-  (let ((path (gitsy-config-worktree-path gitsy--config)))
-    (if (file-name-absolute-p path)
-        path
-      (expand-file-name path gitsy--repo-root))))
+  "Create a new worktree for BRANCH-NAME and return its path.
+If BASE-BRANCH is provided, use it as the starting point, otherwise
+start from the current HEAD."
+  (let ((result (gitsy--call "create" branch-name
+                             (if base-branch "--base" "--head")
+                             base-branch)))
+    (if-let ((hook-error (alist-get 'hook_error result)))
+        (display-warning 'gitsy (format "Created worktree for branch '%s', but %s"
+                                        branch-name hook-error))
+      (message "Created worktree for branch '%s'" branch-name))
+    (alist-get 'path result)))
+
+(defun gitsy--delete-worktree (worktree mode &optional force delete-unmerged
+                                        discard-changes)
+  "Delete WORKTREE, and its branch as MODE says.
+MODE is \"worktree\", \"local-branch\" or \"remote-branch\".
+If FORCE is non-nil, delete it even if its branch is out of sync or its
+detached HEAD is not on any branch or tag.
+If DELETE-UNMERGED is non-nil, delete the branch even if it is not known
+to be merged.
+If DISCARD-CHANGES is non-nil, uncommitted changes in it are thrown away."
+  (let ((result (gitsy--call "delete" (gitsy--worktree-name worktree)
+                             "--yes" "--mode" mode
+                             (and force "--force")
+                             (and delete-unmerged "--delete-unmerged")
+                             (and discard-changes "--discard-changes"))))
+    (if-let ((hook-error (alist-get 'hook_error result)))
+        (display-warning 'gitsy (format "Removed worktree at '%s', but %s"
+                                        (gitsy-worktree-path worktree) hook-error))
+      (message "Removed worktree at '%s'" (gitsy-worktree-path worktree)))))
+
+(defun gitsy--worktree-name (worktree)
+  "Return how gitsy subcommands name WORKTREE.
+That is its branch, or the directory name of a detached worktree."
+  (or (gitsy-worktree-branch worktree)
+      (file-name-nondirectory
+       (directory-file-name (gitsy-worktree-path worktree)))))
+
+(defun gitsy--read-delete-mode ()
+  "Ask what to remove, defaulting to the configured delete_mode."
+  (let* ((choices '(("worktree only" . "worktree")
+                    ("worktree + local branch" . "local-branch")
+                    ("worktree + local + remote branch" . "remote-branch")))
+         (default (car (rassoc (or (and gitsy--config
+                                        (gitsy-config-delete-mode gitsy--config))
+                                   "worktree")
+                               choices)))
+         (choice (completing-read (format "Remove (default %s): " default)
+                                  choices nil t nil nil default)))
+    (cdr (assoc choice choices))))
 
 ;;; Configuration
 
-(defun gitsy--parse-toml-simple (content)
-  "Parse simple TOML CONTENT.
-Only supports top-level key = \"value\" pairs."
-  (let ((result nil))
-    (dolist (line (split-string content "\n" t))
-      (when (string-match "^\\([a-z_]+\\)\\s-*=\\s-*\"\\([^\"]*\\)\"" line)
-        (let ((key (intern (match-string 1 line)))
-              (value (match-string 2 line)))
-          (push (cons key value) result))))
-    (nreverse result)))
-
 (defun gitsy--load-config (repo-root)
-  "Load configuration for REPO-ROOT.
-Checks .gitsy.toml in repo, falls back to customize variables."
-  (let ((config-file (expand-file-name ".gitsy.toml" repo-root)))
-    (if (file-exists-p config-file)
-        (let* ((content (with-temp-buffer
-                          (insert-file-contents config-file)
-                          (buffer-string)))
-               (parsed (gitsy--parse-toml-simple content)))
-          (gitsy-config-create
-           :worktree-path (or (cdr (assq 'worktree_path parsed))
-                              gitsy-worktree-path
-                              (error "worktree_path not configured"))
-           :default-base-branch (or (cdr (assq 'default_base_branch parsed))
-                                    gitsy-default-base-branch)))
-      ;; No config file
-      (if gitsy-worktree-path
-          (gitsy-config-create
-           :worktree-path gitsy-worktree-path
-           :default-base-branch gitsy-default-base-branch)
-        ;; Run setup wizard
-        (if (gitsy--run-setup-wizard repo-root)
-            (gitsy--load-config repo-root)
-          (error "Gitsy configuration required"))))))
+  "Load the effective configuration for REPO-ROOT.
+This is the global config, .gitsy.toml and .gitsy.local.toml merged,
+as the TUI sees them.  Runs the setup wizard first if none of them
+sets a worktree path yet."
+  (let* ((default-directory repo-root)
+         (config (gitsy--call "config")))
+    (unless (alist-get 'worktree_path config)
+      (unless (gitsy--run-setup-wizard repo-root)
+        (error "Gitsy configuration required"))
+      (setq config (gitsy--call "config")))
+    (gitsy--config-from-json config)))
+
+(defun gitsy--config-from-json (config)
+  "Build a gitsy-config from CONFIG, the result of `gitsy --json config'."
+  (gitsy-config-create
+   :worktree-path (alist-get 'worktree_path config)
+   :default-base-branch (alist-get 'default_base_branch config)
+   :delete-mode (alist-get 'delete_mode config)))
 
 (defun gitsy--run-setup-wizard (repo-root)
   "Run the first-time setup wizard for REPO-ROOT.
 Returns non-nil if config was created."
-  (when (yes-or-no-p "No .gitsy.toml found. Run setup wizard? ")
-    (let* ((worktree-path
+  (when (yes-or-no-p "No worktree path configured. Run setup wizard? ")
+    (let* ((default-directory repo-root)
+           (worktree-path
             (read-string
              "Worktree directory (relative to repo or absolute): "
-             "../worktrees"))
+             (or gitsy-worktree-path "../worktrees")))
            (default-base
-            (let ((input (read-string
-                          "Default base branch (e.g., origin/main, empty to skip): ")))
-              (unless (string-empty-p input) input)))
-           (config-content
-            (concat
-             (format "worktree_path = \"%s\"\n" worktree-path)
-             (when default-base
-               (format "default_base_branch = \"%s\"\n" default-base)))))
-      (with-temp-file (expand-file-name ".gitsy.toml" repo-root)
-        (insert config-content))
-      (message "Created .gitsy.toml")
+            (read-string
+             "Default base branch (e.g., origin/main, empty to skip): "
+             gitsy-default-base-branch)))
+      (gitsy--call "config"
+                   "--worktree-path" worktree-path
+                   "--default-base-branch" default-base)
+      (message "Saved the configuration to .gitsy.toml")
       t)))
 
 ;;; Major Mode and Keymap
@@ -400,6 +362,7 @@ Returns non-nil if config was created."
     ;; Actions
     (define-key map (kbd "c") #'gitsy-create)
     (define-key map (kbd "k") #'gitsy-delete)
+    (define-key map (kbd "x") #'gitsy-prune-merged)
     (define-key map (kbd "RET") #'gitsy-visit)
     ;; Buffer operations
     (define-key map (kbd "g") #'gitsy-refresh)
@@ -469,13 +432,9 @@ Returns non-nil if config was created."
     (insert "\n")))
 
 (defun gitsy--managed-worktrees ()
-  "Return worktrees that are in the gitsy worktree directory."
-  (let ((worktree-dir (gitsy--resolve-worktree-path)))
-    (seq-filter (lambda (wt)
-                  (and (not (gitsy-worktree-bare-p wt))
-                       (string-prefix-p worktree-dir
-                                        (gitsy-worktree-path wt))))
-                gitsy--worktrees)))
+  "Return worktrees that are in the gitsy worktree directory.
+The binary only lists worktrees inside the configured directory."
+  (seq-remove #'gitsy-worktree-bare-p gitsy--worktrees))
 
 (defun gitsy--insert-worktree (worktree)
   "Insert a single WORKTREE section."
@@ -483,12 +442,16 @@ Returns non-nil if config was created."
     (let* ((branch (or (gitsy-worktree-branch worktree) "(detached)"))
            (path (gitsy-worktree-path worktree))
            (sync (gitsy-worktree-sync-status worktree))
-           (sync-str (gitsy--sync-status-string sync))
+           (sync-str (gitsy--sync-status-string worktree))
            (sync-face (gitsy--sync-status-face sync)))
       (insert "  ")
       (insert (propertize branch 'face 'gitsy-branch-face))
       (insert " ")
       (insert (propertize (format "[%s]" sync-str) 'face sync-face))
+      (pcase (gitsy-worktree-merge-status worktree)
+        ('merged (insert (propertize " [merged]" 'face 'gitsy-sync-ok-face)))
+        ('squash-merged
+         (insert (propertize " [squash-merged]" 'face 'gitsy-sync-ok-face))))
       (insert "\n")
       (insert (format "    %s\n"
                       (propertize path 'face 'gitsy-directory-face))))))
@@ -501,6 +464,7 @@ Returns non-nil if config was created."
   ["Gitsy Commands"
    ("c" "Create worktree" gitsy-create)
    ("k" "Delete worktree" gitsy-delete)
+   ("x" "Clean up merged worktrees" gitsy-prune-merged)
    ("RET" "Visit worktree" gitsy-visit)
    ("g" "Refresh" gitsy-refresh)]
   ["Navigation"
@@ -527,15 +491,11 @@ Returns non-nil if config was created."
   "Create worktree using default base branch."
   :transient nil
   (interactive)
-  (let* ((branch-name (read-string "New branch name: "))
-         (base-branch (gitsy-config-default-base-branch gitsy--config))
-         (worktree-path (expand-file-name branch-name (gitsy--resolve-worktree-path))))
+  (let ((branch-name (read-string "New branch name: "))
+        (base-branch (gitsy-config-default-base-branch gitsy--config)))
     (when (string-empty-p branch-name)
       (user-error "Branch name cannot be empty"))
-    (gitsy--create-worktree branch-name base-branch)
-    (if gitsy-open-worktree-after-create
-        (gitsy--open-worktree-project worktree-path)
-      (gitsy-refresh))))
+    (gitsy--after-create (gitsy--create-worktree branch-name base-branch))))
 
 (transient-define-suffix gitsy-create-from-local ()
   "Create worktree from a local branch."
@@ -544,15 +504,11 @@ Returns non-nil if config was created."
   (let ((local-branches (gitsy--list-local-branches)))
     (unless local-branches
       (user-error "No local branches found"))
-    (let* ((base-branch (completing-read "Base branch: " local-branches nil t))
-           (branch-name (read-string "New branch name: "))
-           (worktree-path (expand-file-name branch-name (gitsy--resolve-worktree-path))))
+    (let ((base-branch (completing-read "Base branch: " local-branches nil t))
+          (branch-name (read-string "New branch name: ")))
       (when (string-empty-p branch-name)
         (user-error "Branch name cannot be empty"))
-      (gitsy--create-worktree branch-name base-branch)
-      (if gitsy-open-worktree-after-create
-          (gitsy--open-worktree-project worktree-path)
-        (gitsy-refresh)))))
+      (gitsy--after-create (gitsy--create-worktree branch-name base-branch)))))
 
 (transient-define-suffix gitsy-create-from-remote ()
   "Create worktree from a remote branch."
@@ -564,8 +520,7 @@ Returns non-nil if config was created."
     (let* ((remote (if (= (length remotes) 1)
                        (car remotes)
                      (completing-read "Remote: " remotes nil t)))
-           (_ (gitsy--fetch-remote remote))
-           (remote-branches (gitsy--list-remote-branches remote)))
+           (remote-branches (gitsy--fetch-remote remote)))
       (unless remote-branches
         (user-error "No remote branches found"))
       ;; Present branch names without the remote prefix, defaulting to "main".
@@ -582,27 +537,25 @@ Returns non-nil if config was created."
                                "Remote branch: ")
                              branch-names nil t nil nil default-base))
              (base-branch (concat prefix remote-branch))
-             (branch-name (read-string "New branch name: " remote-branch))
-             (worktree-path (expand-file-name branch-name (gitsy--resolve-worktree-path))))
+             (branch-name (read-string "New branch name: " remote-branch)))
         (when (string-empty-p branch-name)
           (user-error "Branch name cannot be empty"))
-        (gitsy--create-worktree branch-name base-branch)
-        (if gitsy-open-worktree-after-create
-            (gitsy--open-worktree-project worktree-path)
-          (gitsy-refresh))))))
+        (gitsy--after-create (gitsy--create-worktree branch-name base-branch))))))
 
 (transient-define-suffix gitsy-create-from-head ()
   "Create worktree from current HEAD."
   :transient nil
   (interactive)
-  (let* ((branch-name (read-string "New branch name: "))
-         (worktree-path (expand-file-name branch-name (gitsy--resolve-worktree-path))))
+  (let ((branch-name (read-string "New branch name: ")))
     (when (string-empty-p branch-name)
       (user-error "Branch name cannot be empty"))
-    (gitsy--create-worktree branch-name nil)
-    (if gitsy-open-worktree-after-create
-        (gitsy--open-worktree-project worktree-path)
-      (gitsy-refresh))))
+    (gitsy--after-create (gitsy--create-worktree branch-name nil))))
+
+(defun gitsy--after-create (path)
+  "Open the worktree just created at PATH, or refresh the buffer."
+  (if gitsy-open-worktree-after-create
+      (gitsy--open-worktree-project path)
+    (gitsy-refresh)))
 
 ;;; Commands
 
@@ -618,18 +571,73 @@ Returns non-nil if config was created."
   (let ((section (magit-current-section)))
     (if (and section (eq (oref section type) 'gitsy-worktree))
         (let* ((worktree (oref section value))
-               (branch (or (gitsy-worktree-branch worktree) "(detached)"))
+               (branch (gitsy--worktree-name worktree))
                (sync-status (gitsy-worktree-sync-status worktree))
-               (warning (unless (eq sync-status 'synced)
-                          (format "WARNING: Branch '%s' is NOT in sync with upstream!\n\n"
-                                  branch)))
+               (warning (when (and (gitsy-worktree-branch worktree)
+                                   (gitsy--sync-at-risk-p sync-status))
+                          (format "WARNING: Branch '%s' may have unpushed commits (%s)!\n\n"
+                                  branch (gitsy--sync-status-string worktree))))
                (prompt (format "%sDelete worktree for '%s'? "
                                (or warning "") branch)))
           (when (yes-or-no-p prompt)
-            (gitsy--delete-worktree worktree (not (null warning)))
+            ;; A detached worktree has no branch to delete with it
+            (let ((mode (if (gitsy-worktree-branch worktree)
+                            (gitsy--read-delete-mode)
+                          "worktree"))
+                  (force (not (null warning)))
+                  delete-unmerged discard-changes done)
+              ;; Each refusal is asked about once, then the delete is retried
+              (while (not done)
+                (condition-case err
+                    (progn
+                      (gitsy--delete-worktree worktree mode force
+                                              delete-unmerged discard-changes)
+                      (setq done t))
+                  (error
+                   (let ((text (error-message-string err)))
+                     (cond
+                      ((and (not discard-changes)
+                            (string-match-p "uncommitted changes" text)
+                            (yes-or-no-p "Worktree has uncommitted changes. Discard them and remove it anyway? "))
+                       (setq discard-changes t))
+                      ((and (not force)
+                            (string-match-p "not on any branch or tag" text)
+                            (yes-or-no-p "Its commits are not on any branch or tag and will be lost. Delete it anyway? "))
+                       (setq force t))
+                      ((and (not delete-unmerged)
+                            (string-match-p "not known to be merged" text)
+                            (yes-or-no-p
+                             (format (if (equal mode "remote-branch")
+                                         "Branch '%s' is not known to be merged. Delete it and its remote branch anyway? "
+                                       "Branch '%s' is not merged or pushed. Delete it anyway? ")
+                                     branch)))
+                       (setq delete-unmerged t))
+                      (t (signal (car err) (cdr err)))))))))
             (gitsy-refresh)))
       (user-error "No worktree at point"))))
 
+(defun gitsy-prune-merged ()
+  "Remove the worktrees of merged branches and branches whose upstream is gone.
+Worktrees with uncommitted changes are left alone."
+  (interactive)
+  (let* ((entries (gitsy--call "prune-merged" "--dry-run"))
+         (pending (seq-filter (lambda (entry)
+                                (equal (alist-get 'status entry) "would_remove"))
+                              entries)))
+    (if (null pending)
+        (message "No merged or stale worktrees to remove")
+      (when (yes-or-no-p
+             (format "Remove %d worktree(s): %s? "
+                     (length pending)
+                     (mapconcat (lambda (entry) (alist-get 'branch entry))
+                                pending ", ")))
+        (let* ((results (gitsy--call "prune-merged" "--yes"))
+               (removed (seq-count (lambda (entry)
+                                     (equal (alist-get 'status entry) "removed"))
+                                   results)))
+          (message "Removed %d of %d worktree(s)" removed (length pending)))
+        (gitsy-refresh)))))
+
 (defun gitsy-visit ()
   "Visit the worktree at point as a projectile project."
   (interactive)
@@ -0,0 +1,346 @@
+use anyhow::Result;
+use git2::{BranchType, Reference, Repository};
+use std::path::{Path, PathBuf};
+
+use crate::worktree::Worktree;
+
+/// What creating a worktree for a branch name would run into, checked as the
+/// name is typed on the create screen.
+#[derive(Debug, Clone, Default)]
+pub struct BranchNameCheck {
+    /// Why git would reject the name, or `None` if it is a valid branch name
+    pub invalid: Option<&'static str>,
+    /// A local branch with this name already exists
+    pub local: bool,
+    /// Remote-tracking branches with this name, e.g. `origin/feature`
+    pub remote: Vec<String>,
+    /// The worktree that already has the branch checked out
+    pub checked_out: Option<PathBuf>,
+    /// The directory the new worktree would be created in
+    pub dir: Option<PathBuf>,
+    pub dir_exists: bool,
+}
+
+/// How to put an existing branch into a new worktree instead of creating it
+/// with `-b`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Checkout {
+    Local,
+    /// Create the local branch tracking this remote-tracking branch
+    Remote(String),
+}
+
+impl BranchNameCheck {
+    /// `dir` is where the worktree would go, or `None` when the configured
+    /// template can't produce a path for the name.
+    pub fn of(
+        repo: &Repository,
+        branch_name: &str,
+        dir: Option<PathBuf>,
+        worktrees: &[Worktree],
+    ) -> Result<Self> {
+        let mut check = Self {
+            dir_exists: dir.as_deref().is_some_and(Path::exists),
+            dir,
+            ..Default::default()
+        };
+
+        check.invalid = invalid_reason(branch_name);
+        if check.invalid.is_some() {
+            return Ok(check);
+        }
+
+        check.local = repo.find_branch(branch_name, BranchType::Local).is_ok();
+        for remote in repo.remotes()?.iter().flatten() {
+            let remote_branch = format!("{}/{}", remote, branch_name);
+            if repo.find_branch(&remote_branch, BranchType::Remote).is_ok() {
+                check.remote.push(remote_branch);
+            }
+        }
+        check.checked_out = worktrees
+            .iter()
+            .find(|worktree| worktree.branch.as_deref() == Some(branch_name))
+            .map(|worktree| worktree.path.clone());
+
+        Ok(check)
+    }
+
+    /// Whether `git worktree add` is bound to fail, so Enter shouldn't try.
+    pub fn blocks_creation(&self) -> bool {
+        self.invalid.is_some() || self.dir.is_none() || self.checked_out.is_some()
+    }
+
+    /// The existing branch to check out, preferring the local branch and then
+    /// the `origin` remote.
+    pub fn checkout(&self) -> Option<Checkout> {
+        if self.blocks_creation() {
+            return None;
+        }
+        if self.local {
+            return Some(Checkout::Local);
+        }
+        self.remote
+            .iter()
+            .find(|remote_branch| remote_branch.starts_with("origin/"))
+            .or_else(|| self.remote.first())
+            .map(|remote_branch| Checkout::Remote(remote_branch.clone()))
+    }
+
+    /// Whether there is something to point out even though creation can go
+    /// ahead.
+    pub fn has_warning(&self) -> bool {
+        self.dir_exists || self.checkout().is_some()
+    }
+
+    /// One line of feedback for the create screen.
+    pub fn summary(&self, branch_name: &str) -> String {
+        if let Some(reason) = self.invalid {
+            return format!("Invalid branch name: {}", reason);
+        }
+        let Some(ref dir) = self.dir else {
+            return "worktree_dir_template gives no usable path for this name".to_string();
+        };
+        if let Some(ref path) = self.checked_out {
+            return format!(
+                "Branch '{}' is already checked out at {}",
+                branch_name,
+                path.display()
+            );
+        }
+
+        let mut notes = Vec::new();
+        match self.checkout() {
+            Some(Checkout::Local) => notes.push(format!(
+                "Branch '{}' already exists; Enter checks it out instead of creating it",
+                branch_name
+            )),
+            Some(Checkout::Remote(ref remote_branch)) => notes.push(format!(
+                "Branch '{}' exists on the remote; Enter checks out {} instead of creating it",
+                branch_name, remote_branch
+            )),
+            None => {}
+        }
+        if self.dir_exists {
+            notes.push(format!("{} already exists", dir.display()));
+        }
+
+        if notes.is_empty() {
+            format!("New branch, created at {}", dir.display())
+        } else {
+            notes.join("; ")
+        }
+    }
+}
+
+/// Why git won't accept `branch_name`, following `git check-ref-format
+/// --branch`. The common mistakes get a specific reason.
+pub fn invalid_reason(branch_name: &str) -> Option<&'static str> {
+    if branch_name.is_empty() {
+        return Some("the name is empty");
+    }
+    if branch_name == "HEAD" || branch_name.starts_with('-') {
+        return Some("it can't be HEAD or start with '-'");
+    }
+    if Reference::is_valid_name(&format!("refs/heads/{}", branch_name)) {
+        return None;
+    }
+
+    Some(if branch_name.chars().any(char::is_whitespace) {
+        "it can't contain spaces"
+    } else if branch_name.contains("..") {
+        "it can't contain '..'"
+    } else if branch_name.contains(['~', '^', ':', '?', '*', '[', '\\']) {
+        "it can't contain ~ ^ : ? * [ or \\"
+    } else if branch_name.ends_with(".lock") || branch_name.ends_with('.') {
+        "it can't end with '.lock' or '.'"
+    } else if branch_name.starts_with('/')
+        || branch_name.ends_with('/')
+        || branch_name.contains("//")
+    {
+        "it can't start or end with '/' or contain '//'"
+    } else {
+        "git doesn't allow this name"
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{RepositoryInitOptions, Signature};
+    use tempfile::TempDir;
+
+    /// A repository with a `main` commit, a local `feature` branch, and
+    /// `shared` on both the `mirror` and `origin` remotes.
+    fn repository() -> (TempDir, Repository) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init_opts(
+            dir.path().join("repo"),
+            RepositoryInitOptions::new().initial_head("main"),
+        )
+        .unwrap();
+        {
+            let signature = Signature::now("Gitsy", "gitsy@example.com").unwrap();
+            let tree = repo
+                .find_tree(repo.index().unwrap().write_tree().unwrap())
+                .unwrap();
+            let id = repo
+                .commit(Some("HEAD"), &signature, &signature, "Initial", &tree, &[])
+                .unwrap();
+            let commit = repo.find_commit(id).unwrap();
+            repo.branch("feature", &commit, false).unwrap();
+            for remote in ["mirror", "origin"] {
+                repo.remote(remote, &format!("https://example.com/{}.git", remote))
+                    .unwrap();
+                repo.reference(
+                    &format!("refs/remotes/{}/shared", remote),
+                    id,
+                    false,
+                    "test",
+                )
+                .unwrap();
+            }
+        }
+        (dir, repo)
+    }
+
+    fn check(repo: &Repository, branch_name: &str, worktrees: &[Worktree]) -> BranchNameCheck {
+        let dir = Path::new("/wt").join(branch_name);
+        BranchNameCheck::of(repo, branch_name, Some(dir), worktrees).unwrap()
+    }
+
+    #[test]
+    fn explains_invalid_names() {
+        for (name, reason) in [
+            ("", "the name is empty"),
+            ("HEAD", "it can't be HEAD or start with '-'"),
+            ("-x", "it can't be HEAD or start with '-'"),
+            ("my branch", "it can't contain spaces"),
+            ("a..b", "it can't contain '..'"),
+            ("what?", "it can't contain ~ ^ : ? * [ or \\"),
+            ("fix:bug", "it can't contain ~ ^ : ? * [ or \\"),
+            ("feature.lock", "it can't end with '.lock' or '.'"),
+            ("feature.", "it can't end with '.lock' or '.'"),
+            ("feature/", "it can't start or end with '/' or contain '//'"),
+            ("a//b", "it can't start or end with '/' or contain '//'"),
+            ("a/.b", "git doesn't allow this name"),
+            ("@{x}", "git doesn't allow this name"),
+        ] {
+            assert_eq!(invalid_reason(name), Some(reason), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn accepts_valid_names() {
+        for name in [
+            "feature",
+            "feature/login",
+            "release-1.2",
+            "fix_42",
+            "ünïcode",
+        ] {
+            assert_eq!(invalid_reason(name), None, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn new_name_can_be_created() {
+        let (_dir, repo) = repository();
+
+        let check = check(&repo, "brand-new", &[]);
+
+        assert!(!check.blocks_creation() && !check.has_warning());
+        assert_eq!(check.checkout(), None);
+        assert_eq!(
+            check.summary("brand-new"),
+            "New branch, created at /wt/brand-new"
+        );
+    }
+
+    #[test]
+    fn invalid_name_skips_the_other_checks() {
+        let (_dir, repo) = repository();
+
+        let check = check(&repo, "feature..", &[]);
+
+        assert!(check.blocks_creation());
+        assert!(!check.local);
+        assert_eq!(
+            check.summary("feature.."),
+            "Invalid branch name: it can't contain '..'"
+        );
+    }
+
+    #[test]
+    fn existing_local_branch_is_checked_out() {
+        let (_dir, repo) = repository();
+
+        let check = check(&repo, "feature", &[]);
+
+        assert!(check.local && check.remote.is_empty());
+        assert_eq!(check.checkout(), Some(Checkout::Local));
+        assert!(check.has_warning() && !check.blocks_creation());
+    }
+
+    #[test]
+    fn remote_branch_prefers_origin() {
+        let (_dir, repo) = repository();
+
+        let check = check(&repo, "shared", &[]);
+
+        // Listed in the order of the remotes, but origin wins
+        assert!(!check.local);
+        assert_eq!(check.remote, ["mirror/shared", "origin/shared"]);
+        assert_eq!(
+            check.checkout(),
+            Some(Checkout::Remote("origin/shared".to_string()))
+        );
+    }
+
+    #[test]
+    fn branch_checked_out_elsewhere_blocks_creation() {
+        let (_dir, repo) = repository();
+        let worktrees = [Worktree {
+            path: PathBuf::from("/wt/feature"),
+            branch: Some("feature".to_string()),
+            ..Default::default()
+        }];
+
+        let check = check(&repo, "feature", &worktrees);
+
+        assert_eq!(check.checked_out, Some(PathBuf::from("/wt/feature")));
+        assert!(check.blocks_creation());
+        assert_eq!(check.checkout(), None);
+        assert_eq!(
+            check.summary("feature"),
+            "Branch 'feature' is already checked out at /wt/feature"
+        );
+    }
+
+    #[test]
+    fn existing_directory_is_pointed_out() {
+        let (dir, repo) = repository();
+        let path = dir.path().join("wt/brand-new");
+        std::fs::create_dir_all(&path).unwrap();
+
+        let check = BranchNameCheck::of(&repo, "brand-new", Some(path.clone()), &[]).unwrap();
+
+        assert!(check.dir_exists && check.has_warning() && !check.blocks_creation());
+        assert_eq!(
+            check.summary("brand-new"),
+            format!("{} already exists", path.display())
+        );
+    }
+
+    #[test]
+    fn name_without_a_path_blocks_creation() {
+        let (_dir, repo) = repository();
+
+        let check = BranchNameCheck::of(&repo, "brand-new", None, &[]).unwrap();
+
+        assert!(check.blocks_creation());
+        assert_eq!(
+            check.summary("brand-new"),
+            "worktree_dir_template gives no usable path for this name"
+        );
+    }
+}
@@ -0,0 +1,984 @@
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    App, CLEANUP_PROMPT, DELETE_PROMPT, DeleteMode, GitsyConfig, branch_deletion_warning,
+    branch_name, changes_warning,
+    config::{self, Layer},
+    delete_success, delete_warning, detached_warning, find_git_root,
+    git_backend::GitBackendKind,
+    hooks::HookEvent,
+    keeps_branch, load_config, merge_summary,
+    prune::{self, PruneCandidate, PruneResult},
+    shared_files::SharedFile,
+    shell::{self, Shell},
+    sync::{MergeStatus, SyncStatus},
+    worktree::{Worktree, WorktreeChanges},
+};
+
+/// A terminal-based Git worktree manager.
+///
+/// Run without a subcommand to open the interactive TUI.
+#[derive(Debug, Parser)]
+#[command(name = "gitsy", version, about)]
+pub struct Cli {
+    /// Print a single JSON document on stdout instead of human-readable output.
+    /// Failures are reported as `{"error": "..."}` with a non-zero exit code.
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Write the worktree to change into to this file (or a path such as
+    /// /dev/fd/3) for the shell wrapper from `gitsy init`
+    #[arg(long, global = true, value_name = "PATH")]
+    pub cd_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Create a new branch and worktree
+    Create(CreateArgs),
+    /// Remove the worktree for a branch
+    Delete(DeleteArgs),
+    /// List the worktrees managed by gitsy
+    List(ListArgs),
+    /// Remove the worktrees of branches that are merged into the default base
+    /// branch or whose upstream is gone
+    PruneMerged(PruneMergedArgs),
+    /// Fetch a remote and list its branches
+    Fetch(FetchArgs),
+    /// List the configured remotes
+    Remotes,
+    /// Show the configuration, or update .gitsy.toml when options are given
+    Config(ConfigArgs),
+    /// Print a shell function that changes into the worktree gitsy switches
+    /// to or creates
+    Init(InitArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CreateArgs {
+    /// Name of the new branch, or with --detach the tag or commit to check out
+    pub branch: String,
+
+    /// Base the new branch on this ref instead of the configured default
+    #[arg(long, value_name = "REF", conflicts_with_all = ["from_remote", "head"])]
+    pub base: Option<String>,
+
+    /// Fetch the remote first, then base the new branch on <REMOTE>/<BRANCH>
+    #[arg(long, value_name = "REMOTE/BRANCH", conflicts_with = "head")]
+    pub from_remote: Option<String>,
+
+    /// Base the new branch on the current HEAD, ignoring the configured default
+    #[arg(long)]
+    pub head: bool,
+
+    /// Check out <BRANCH> as a tag or commit with a detached HEAD instead of
+    /// creating a branch
+    #[arg(long, conflicts_with_all = ["base", "from_remote", "head"])]
+    pub detach: bool,
+
+    /// Check out <BRANCH>, which already exists locally or on a remote,
+    /// instead of creating it
+    #[arg(long, conflicts_with_all = ["base", "from_remote", "head", "detach"])]
+    pub checkout: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DeleteArgs {
+    /// Branch whose worktree should be removed, or the directory name of a
+    /// detached worktree
+    pub branch: String,
+
+    /// What to remove; defaults to `delete_mode` from .gitsy.toml
+    #[arg(long, value_enum)]
+    pub mode: Option<DeleteMode>,
+
+    /// Delete even if the branch is not in sync with its upstream, or a
+    /// detached worktree's HEAD is not on any branch or tag
+    #[arg(long)]
+    pub force: bool,
+
+    /// With a --mode that deletes the branch, delete it even if it is not
+    /// known to be merged
+    #[arg(long)]
+    pub delete_unmerged: bool,
+
+    /// Remove the worktree even if it has uncommitted or untracked changes,
+    /// discarding them
+    #[arg(long)]
+    pub discard_changes: bool,
+
+    /// Don't ask for confirmation (required with --json)
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PruneMergedArgs {
+    /// Only show which worktrees would be removed
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// What to remove; defaults to `delete_mode` from .gitsy.toml
+    #[arg(long, value_enum)]
+    pub mode: Option<DeleteMode>,
+
+    /// Also remove worktrees with uncommitted or untracked changes, discarding
+    /// them; without this they are skipped
+    #[arg(long)]
+    pub discard_changes: bool,
+
+    /// Also delete branches whose upstream is gone but that are not known to
+    /// be merged; without this only their worktrees are removed
+    #[arg(long)]
+    pub delete_unmerged: bool,
+
+    /// Don't ask for confirmation (required with --json unless --dry-run)
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    /// Print line-oriented output in the style of `git worktree list --porcelain`
+    #[arg(long)]
+    pub porcelain: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct FetchArgs {
+    /// Remote to fetch from
+    pub remote: String,
+}
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    /// Shell to print the wrapper function for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    /// Set the directory where worktrees are created; may start with `~` and
+    /// contain `{repo}`
+    #[arg(long, value_name = "PATH")]
+    pub worktree_path: Option<String>,
+
+    /// Set the default base branch; pass an empty string to clear it
+    #[arg(long, value_name = "REF")]
+    pub default_base_branch: Option<String>,
+
+    /// Set what deleting a worktree removes by default
+    #[arg(long, value_enum)]
+    pub delete_mode: Option<DeleteMode>,
+
+    /// Set the command worktrees are opened with; pass an empty string to
+    /// fall back to $VISUAL or $EDITOR
+    #[arg(long, value_name = "COMMAND")]
+    pub editor: Option<String>,
+
+    /// Set whether git operations go through libgit2 or the `git` command
+    #[arg(long, value_enum)]
+    pub git_backend: Option<GitBackendKind>,
+
+    /// Write to .gitsy.local.toml, which overrides .gitsy.toml and is kept
+    /// out of git
+    #[arg(long, conflicts_with = "global")]
+    pub local: bool,
+
+    /// Write to the global config, which applies to every repository
+    #[arg(long)]
+    pub global: bool,
+}
+
+/// Where command results go: human-readable text, or a single line of JSON
+/// on stdout when `--json` is given.
+struct Output {
+    json: bool,
+    /// Where to tell the shell wrapper which worktree to change into
+    cd_file: Option<PathBuf>,
+}
+
+impl Output {
+    fn emit<T: Serialize>(&self, value: &T, human: impl FnOnce(&T)) -> Result<()> {
+        if self.json {
+            println!("{}", serde_json::to_string(value)?);
+        } else {
+            human(value);
+        }
+        Ok(())
+    }
+
+    fn change_directory(&self, worktree: &Path) -> Result<()> {
+        match self.cd_file {
+            Some(ref cd_file) => shell::write_cd_file(cd_file, worktree),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn run(command: Commands, json: bool, cd_file: Option<PathBuf>) -> Result<()> {
+    let output = Output { json, cd_file };
+
+    match dispatch(command, &output) {
+        Err(e) if json => {
+            println!("{}", serde_json::json!({ "error": format!("{:#}", e) }));
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+fn dispatch(command: Commands, output: &Output) -> Result<()> {
+    match command {
+        Commands::Create(args) => create(&mut open_app()?, args, output),
+        Commands::Delete(args) => delete(&mut open_app()?, args, output),
+        Commands::List(args) => list(&open_app()?, args, output),
+        Commands::PruneMerged(args) => prune_merged(&open_app()?, args, output),
+        Commands::Fetch(args) => fetch(&mut open_app()?, args, output),
+        Commands::Remotes => remotes(&mut open_app()?, output),
+        Commands::Config(args) => config(&find_git_root()?, args, output),
+        // The wrapper is usually installed from outside any repository
+        Commands::Init(args) => init(args, output),
+    }
+}
+
+fn open_app() -> Result<App> {
+    let repo_root = find_git_root()?;
+    let config = load_config(&repo_root)?;
+    Ok(App::new(repo_root, config))
+}
+
+/// Sync fields shared by `list` and `delete` output. Every field is `null`
+/// when the state can't be determined, e.g. for detached worktrees.
+#[derive(Debug, Serialize)]
+struct SyncReport {
+    #[serde(skip)]
+    status: Option<SyncStatus>,
+    /// `"in_sync"`, or `"out_of_sync"` when deleting could lose commits
+    sync: Option<&'static str>,
+    /// One of `synced`, `ahead`, `behind`, `diverged`, `no_upstream`, `upstream_gone`
+    sync_state: Option<&'static str>,
+    ahead: Option<usize>,
+    behind: Option<usize>,
+}
+
+impl SyncReport {
+    fn new(status: Option<SyncStatus>) -> Self {
+        Self {
+            status,
+            sync: status.map(|status| {
+                if status.is_at_risk() {
+                    "out_of_sync"
+                } else {
+                    "in_sync"
+                }
+            }),
+            sync_state: status.map(|status| status.name()),
+            ahead: status.map(|status| status.ahead()),
+            behind: status.map(|status| status.behind()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CreateReport {
+    branch: String,
+    path: PathBuf,
+    /// The ref the branch was created from, or `null` for the current HEAD
+    base: Option<String>,
+    /// What happened to each file matched by `copy` and `symlink`
+    files: Vec<SharedFile>,
+    /// Why the `post_create` hooks failed, or `null`; the worktree is kept
+    hook_error: Option<String>,
+    /// The tmux session created for the worktree, or `null` when the tmux
+    /// integration is off or the session couldn't be created
+    tmux_session: Option<String>,
+    /// Whether `branch` was checked out as a tag or commit with a detached
+    /// HEAD rather than created as a branch
+    detached: bool,
+    /// The commit checked out in the worktree
+    head: Option<String>,
+}
+
+fn create(app: &mut App, args: CreateArgs, output: &Output) -> Result<()> {
+    if !args.detach
+        && let Some(reason) = branch_name::invalid_reason(&args.branch)
+    {
+        return Err(anyhow::anyhow!(
+            "Invalid branch name '{}': {}",
+            args.branch,
+            reason
+        ));
+    }
+
+    let base = if args.detach || args.checkout {
+        None
+    } else if let Some(base) = args.base {
+        Some(base)
+    } else if let Some(spec) = args.from_remote {
+        Some(fetch_remote_base(app, &spec)?)
+    } else if args.head {
+        None
+    } else {
+        app.config.default_base_branch.clone()
+    };
+
+    let worktree = if args.detach {
+        app.create_detached_worktree(&args.branch)?
+    } else if args.checkout {
+        let check = app.branch_name_check(&args.branch)?;
+        if check.blocks_creation() {
+            return Err(anyhow::anyhow!("{}", check.summary(&args.branch)));
+        }
+        let checkout = check.checkout().with_context(|| {
+            format!(
+                "Branch '{}' doesn't exist locally or on a remote",
+                args.branch
+            )
+        })?;
+        app.checkout_worktree(&args.branch, &checkout)?
+    } else {
+        app.create_worktree(&args.branch, base.as_deref())?
+    };
+    let files = app.share_files(&worktree.path);
+    if !output.json {
+        for file in &files {
+            eprintln!("{}", file);
+        }
+    }
+
+    // Hook output goes to stderr so stdout stays parseable
+    let hook_error = app
+        .hook_context(&worktree)
+        .run(
+            HookEvent::PostCreate,
+            app.config.hooks.commands(HookEvent::PostCreate),
+            &mut |line| eprintln!("{}", line),
+        )
+        .err()
+        .map(|e| e.to_string());
+
+    let tmux_session = if app.config.tmux.enabled {
+        match app
+            .config
+            .tmux
+            .ensure_session(&worktree.name(), &worktree.path)
+        {
+            Ok(session) => Some(session),
+            Err(e) => {
+                eprintln!("Warning: Failed to create tmux session: {:#}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let summary = match worktree.branch {
+        Some(ref branch) => format!("Created worktree for branch '{}'", branch),
+        None => format!("Created worktree detached at {}", worktree.label()),
+    };
+    let report = CreateReport {
+        branch: args.branch,
+        path: worktree.path,
+        base,
+        files,
+        hook_error,
+        tmux_session,
+        detached: worktree.detached,
+        head: worktree.head,
+    };
+    output.change_directory(&report.path)?;
+    output.emit(&report, |report| {
+        eprintln!("{}", summary);
+        if let Some(ref hook_error) = report.hook_error {
+            eprintln!("Warning: {}; the worktree was kept", hook_error);
+        }
+        println!("{}", report.path.display());
+    })
+}
+
+/// Fetches the remote named by the `<remote>/<branch>` spec and checks that
+/// the branch exists there, mirroring the TUI's "Fetch from a remote..." flow.
+fn fetch_remote_base(app: &mut App, spec: &str) -> Result<String> {
+    app.load_remotes()?;
+
+    // Remote names may themselves contain slashes, so prefer the longest match
+    let remote = app
+        .remotes
+        .iter()
+        .filter(|remote| spec.starts_with(&format!("{}/", remote)))
+        .max_by_key(|remote| remote.len())
+        .cloned()
+        .with_context(|| format!("'{}' does not start with a configured remote", spec))?;
+
+    app.fetch_and_load_remote_branches_from(&remote)?;
+
+    if !app.remote_branches.iter().any(|branch| branch == spec) {
+        return Err(anyhow::anyhow!(
+            "Remote branch '{}' not found after fetching {}",
+            spec,
+            remote
+        ));
+    }
+
+    Ok(spec.to_string())
+}
+
+#[derive(Debug, Serialize)]
+struct DeleteReport {
+    branch: String,
+    /// `worktree`, `local-branch` or `remote-branch`
+    mode: DeleteMode,
+    /// The deleted remote branch, e.g. `origin/feature`, or `null` if none
+    /// was deleted
+    remote_branch: Option<String>,
+    /// The sync state at the time of deletion, as in `gitsy list`
+    #[serde(flatten)]
+    sync: SyncReport,
+    /// What the worktree contained when it was removed, or `null` if it
+    /// couldn't be inspected
+    changes: Option<WorktreeChanges>,
+    /// Why the `post_delete` hooks failed, or `null`
+    hook_error: Option<String>,
+}
+
+fn delete(app: &mut App, args: DeleteArgs, output: &Output) -> Result<()> {
+    if output.json && !args.yes {
+        return Err(anyhow::anyhow!("--yes is required with --json"));
+    }
+
+    let worktree = app.named_worktree(&args.branch)?;
+
+    let (mode, status, merge, head_referenced) = match worktree.branch {
+        Some(ref branch) => (
+            args.mode.unwrap_or(app.config.delete_mode),
+            Some(app.branch_sync_status(branch)?),
+            app.branch_merge_status(branch).ok().flatten(),
+            true,
+        ),
+        // A detached worktree has no branch to delete along with it
+        None => (
+            DeleteMode::Worktree,
+            None,
+            None,
+            app.head_referenced(&worktree)?,
+        ),
+    };
+    let changes = app.worktree_changes(&worktree).ok();
+    let branch_warning =
+        status.and_then(|status| branch_deletion_warning(&args.branch, mode, status, merge));
+    if !output.json {
+        eprintln!(
+            "{}",
+            match status {
+                Some(status) => delete_warning(&args.branch, status),
+                None => detached_warning(&worktree, head_referenced),
+            }
+        );
+        if let (Some(merge), Some(base)) = (merge, &app.config.default_base_branch) {
+            eprintln!("{}", merge_summary(&args.branch, merge, base));
+        }
+        if let Some(text) = changes.as_ref().and_then(changes_warning) {
+            eprintln!("{}", text);
+        }
+        if let Some(ref text) = branch_warning {
+            eprintln!("{}", text);
+        }
+        eprintln!("Removing: {}", mode);
+    }
+
+    if let Some(status) = status
+        && status.is_at_risk()
+        && !args.force
+    {
+        return Err(anyhow::anyhow!(
+            "Refusing to delete worktree for branch '{}' ({}) without --force",
+            args.branch,
+            status
+        ));
+    }
+
+    if !head_referenced && !args.force {
+        return Err(anyhow::anyhow!(
+            "Refusing to delete detached worktree '{}', whose commits are not on any branch or tag, without --force",
+            args.branch
+        ));
+    }
+
+    if branch_warning.is_some() && !args.delete_unmerged {
+        return Err(anyhow::anyhow!(
+            "Refusing to delete branch '{}', which is not known to be merged, without --delete-unmerged",
+            args.branch
+        ));
+    }
+
+    if worktree.locked && !args.discard_changes {
+        return Err(anyhow::anyhow!(
+            "Worktree for branch '{}' is locked; use --discard-changes to remove it anyway",
+            args.branch
+        ));
+    }
+
+    let blocked = changes.is_some_and(|changes| changes.blocks_removal());
+    if blocked && !args.discard_changes {
+        return Err(anyhow::anyhow!(
+            "Worktree for branch '{}' has uncommitted changes; use --discard-changes to remove it anyway",
+            args.branch
+        ));
+    }
+
+    if !args.yes && !confirm(DELETE_PROMPT)? {
+        return Err(anyhow::anyhow!("Delete cancelled"));
+    }
+
+    let outcome = app.delete_with_hooks(&worktree, mode, args.discard_changes, &mut |line| {
+        eprintln!("{}", line)
+    })?;
+
+    let report = DeleteReport {
+        branch: args.branch,
+        mode,
+        remote_branch: outcome.remote_branch,
+        sync: SyncReport::new(status),
+        changes,
+        hook_error: outcome.hook_error,
+    };
+    output.emit(&report, |report| {
+        eprintln!(
+            "{}",
+            delete_success(&worktree, report.mode, report.remote_branch.as_deref())
+        );
+        if let Some(ref hook_error) = report.hook_error {
+            eprintln!("Warning: {}", hook_error);
+        }
+    })
+}
+
+/// Asks a yes/no question on stderr; anything but "y" or "yes" means no.
+fn confirm(prompt: &str) -> Result<bool> {
+    eprint!("{} ", prompt);
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    let read = io::stdin()
+        .read_line(&mut answer)
+        .context("Failed to read confirmation")?;
+    if read == 0 {
+        // stdin was closed; finish the prompt line before reporting
+        eprintln!();
+    }
+
+    let answer = answer.trim().to_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
+
+/// One worktree considered by `gitsy prune-merged`.
+#[derive(Debug, Serialize)]
+struct PruneEntry {
+    branch: String,
+    path: PathBuf,
+    /// `merged`, `squash_merged` or `upstream_gone`
+    reason: &'static str,
+    changes: Option<WorktreeChanges>,
+    /// `removed`, `failed`, `skipped` (uncommitted changes without
+    /// `--discard-changes`) or `would_remove` (with `--dry-run`)
+    status: &'static str,
+    error: Option<String>,
+    /// Why the `post_delete` hooks failed after the worktree was removed
+    hook_error: Option<String>,
+    /// Whether the branch is (or with `--dry-run` would be) kept because it
+    /// is not known to be merged
+    kept_branch: bool,
+}
+
+impl PruneEntry {
+    fn new(candidate: &PruneCandidate, status: &'static str, error: Option<String>) -> Self {
+        Self {
+            branch: candidate.branch.clone(),
+            path: candidate.worktree.path.clone(),
+            reason: candidate.reason.name(),
+            changes: candidate.changes,
+            status,
+            error,
+            hook_error: None,
+            kept_branch: false,
+        }
+    }
+}
+
+fn prune_merged(app: &App, args: PruneMergedArgs, output: &Output) -> Result<()> {
+    if output.json && !args.yes && !args.dry_run {
+        return Err(anyhow::anyhow!("--yes is required with --json"));
+    }
+
+    let mode = args.mode.unwrap_or(app.config.delete_mode);
+    let branch_kept =
+        |candidate: &PruneCandidate| !args.delete_unmerged && keeps_branch(candidate, mode);
+    let candidates = app.load_prune_candidates()?;
+    let (to_remove, skipped): (Vec<&PruneCandidate>, Vec<&PruneCandidate>) = candidates
+        .iter()
+        .partition(|candidate| args.discard_changes || !candidate.blocks_removal());
+
+    let mut entries: Vec<PruneEntry> = skipped
+        .iter()
+        .map(|candidate| {
+            PruneEntry::new(
+                candidate,
+                "skipped",
+                Some("Worktree has uncommitted changes".to_string()),
+            )
+        })
+        .collect();
+
+    if !output.json {
+        if candidates.is_empty() {
+            eprintln!("No merged or stale worktrees found");
+        }
+        for candidate in &to_remove {
+            if branch_kept(candidate) {
+                eprintln!(
+                    "{}  ({}) keeping the branch: not known to be merged",
+                    candidate.branch, candidate.reason
+                );
+            } else {
+                eprintln!("{}  ({})", candidate.branch, candidate.reason);
+            }
+        }
+        if !to_remove.is_empty() {
+            if args.dry_run {
+                eprintln!("Would remove: {}", mode);
+            } else {
+                eprintln!("Removing: {}", mode);
+            }
+        }
+        for candidate in &skipped {
+            eprintln!(
+                "{}  ({}) skipped: uncommitted changes ({})",
+                candidate.branch,
+                candidate.reason,
+                candidate.changes.unwrap_or_default()
+            );
+        }
+    }
+
+    let mut results: Vec<PruneResult> = Vec::new();
+    if args.dry_run {
+        entries.extend(to_remove.iter().map(|candidate| PruneEntry {
+            kept_branch: branch_kept(candidate),
+            ..PruneEntry::new(candidate, "would_remove", None)
+        }));
+    } else if !to_remove.is_empty() {
+        if !args.yes && !confirm(CLEANUP_PROMPT)? {
+            return Err(anyhow::anyhow!("Cleanup cancelled"));
+        }
+
+        results = app.prune_worktrees(
+            to_remove.iter().copied(),
+            mode,
+            args.discard_changes,
+            args.delete_unmerged,
+            &mut |line| eprintln!("{}", line),
+        );
+        entries.extend(to_remove.iter().zip(&results).map(|(candidate, result)| {
+            let status = if result.error.is_some() {
+                "failed"
+            } else {
+                "removed"
+            };
+            PruneEntry {
+                hook_error: result.hook_error.clone(),
+                kept_branch: result.kept_branch,
+                ..PruneEntry::new(candidate, status, result.error.clone())
+            }
+        }));
+    }
+
+    // Report in the order the worktrees are listed, not grouped by outcome
+    entries.sort_by_key(|entry| {
+        candidates
+            .iter()
+            .position(|candidate| candidate.branch == entry.branch)
+    });
+
+    output.emit(&entries, |_| {
+        if !results.is_empty() {
+            eprintln!("{}", prune::summary(&results));
+        }
+    })?;
+
+    if results.iter().any(|result| result.error.is_some()) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// One entry of `gitsy list`. The field names form the JSON schema consumed
+/// by other front ends, so they should only ever be added to.
+#[derive(Debug, Serialize)]
+struct ListEntry {
+    #[serde(flatten)]
+    worktree: Worktree,
+    #[serde(flatten)]
+    sync: SyncReport,
+    #[serde(skip)]
+    merge_status: Option<MergeStatus>,
+    /// `merged`, `squash_merged` or `not_merged` relative to
+    /// `default_base_branch`, or `null` when no base is configured
+    merge_state: Option<&'static str>,
+}
+
+fn list(app: &App, args: ListArgs, output: &Output) -> Result<()> {
+    let entries: Vec<ListEntry> = app
+        .load_worktrees()?
+        .into_iter()
+        .map(|worktree| {
+            let branch = worktree.branch.as_deref();
+            let status = branch.and_then(|branch| app.branch_sync_status(branch).ok());
+            let merge_status =
+                branch.and_then(|branch| app.branch_merge_status(branch).ok().flatten());
+            ListEntry {
+                sync: SyncReport::new(status),
+                merge_status,
+                merge_state: merge_status.map(|merge| merge.name()),
+                worktree,
+            }
+        })
+        .collect();
+
+    output.emit(&entries, |entries| {
+        if args.porcelain {
+            for entry in entries {
+                print_porcelain(entry);
+            }
+        } else {
+            print_table(entries);
+        }
+    })
+}
+
+fn print_porcelain(entry: &ListEntry) {
+    let worktree = &entry.worktree;
+
+    println!("worktree {}", worktree.path.display());
+    if let Some(ref head) = worktree.head {
+        println!("HEAD {}", head);
+    }
+    if let Some(ref branch) = worktree.branch {
+        println!("branch {}", branch);
+    }
+    if worktree.bare {
+        println!("bare");
+    }
+    if worktree.detached {
+        println!("detached");
+    }
+    if let Some(ref describe) = worktree.describe {
+        println!("describe {}", describe);
+    }
+    if worktree.locked {
+        println!("locked");
+    }
+    if worktree.prunable {
+        println!("prunable");
+    }
+    if let Some(status) = entry.sync.status {
+        println!("sync {}", entry.sync.sync.unwrap_or_default());
+        println!("sync_state {}", status.name());
+        println!("ahead {}", status.ahead());
+        println!("behind {}", status.behind());
+    }
+    if let Some(merge_state) = entry.merge_state {
+        println!("merge_state {}", merge_state);
+    }
+    println!();
+}
+
+fn print_table(entries: &[ListEntry]) {
+    let rows: Vec<[String; 4]> = entries
+        .iter()
+        .map(|entry| {
+            [
+                entry.worktree.label(),
+                entry
+                    .sync
+                    .status
+                    .map_or_else(|| "-".to_string(), |status| status.to_string()),
+                entry
+                    .merge_status
+                    .map_or_else(|| "-".to_string(), |merge| merge.to_string()),
+                entry.worktree.path.display().to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = [0; 3];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    for [branch, sync, merge, path] in rows {
+        println!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+            branch,
+            sync,
+            merge,
+            path,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        );
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FetchReport {
+    remote: String,
+    /// Remote-tracking branches of the remote, e.g. `origin/main`
+    branches: Vec<String>,
+}
+
+fn fetch(app: &mut App, args: FetchArgs, output: &Output) -> Result<()> {
+    app.fetch_and_load_remote_branches_from(&args.remote)?;
+
+    let report = FetchReport {
+        remote: args.remote,
+        branches: std::mem::take(&mut app.remote_branches),
+    };
+    output.emit(&report, |report| {
+        for branch in &report.branches {
+            println!("{}", branch);
+        }
+    })
+}
+
+fn remotes(app: &mut App, output: &Output) -> Result<()> {
+    app.load_remotes()?;
+
+    output.emit(&app.remotes, |remotes| {
+        for remote in remotes {
+            println!("{}", remote);
+        }
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct ConfigReport {
+    repo_root: PathBuf,
+    /// `worktree_path` expanded and resolved against the repository root,
+    /// or `null` until it is set
+    worktree_root: Option<PathBuf>,
+    /// The config files that were merged, weakest first
+    sources: Vec<PathBuf>,
+    /// Left out until `worktree_path` is set, so front ends can tell that
+    /// the setup wizard is needed
+    #[serde(flatten)]
+    config: Option<GitsyConfig>,
+}
+
+fn config(repo_root: &Path, args: ConfigArgs, output: &Output) -> Result<()> {
+    let updating = args.worktree_path.is_some()
+        || args.default_base_branch.is_some()
+        || args.delete_mode.is_some()
+        || args.editor.is_some()
+        || args.git_backend.is_some();
+
+    if updating {
+        let layer = if args.global {
+            Layer::Global
+        } else if args.local {
+            Layer::Local
+        } else {
+            Layer::Repo
+        };
+
+        // An empty string clears the setting, letting a weaker layer's
+        // value show through
+        let set = |table: &mut toml_edit::Table, key: &str, value: Option<String>| match value {
+            Some(value) if value.is_empty() => {
+                table.remove(key);
+            }
+            Some(value) => {
+                table.insert(key, toml_edit::value(value));
+            }
+            None => {}
+        };
+        config::update(repo_root, layer, |table| {
+            set(table, "worktree_path", args.worktree_path);
+            set(table, "default_base_branch", args.default_base_branch);
+            set(
+                table,
+                "delete_mode",
+                args.delete_mode.map(|mode| mode.name().to_string()),
+            );
+            set(table, "editor", args.editor);
+            set(
+                table,
+                "git_backend",
+                args.git_backend.map(|kind| kind.name().to_string()),
+            );
+        })?;
+    }
+
+    let config = if config::load_merged(repo_root)?.contains_key("worktree_path") {
+        Some(load_config(repo_root)?)
+    } else {
+        None
+    };
+    let app = config.map(|config| App::new(repo_root.to_path_buf(), config));
+    let report = ConfigReport {
+        repo_root: repo_root.to_path_buf(),
+        worktree_root: app.as_ref().map(App::worktree_root),
+        sources: config::sources(repo_root),
+        config: app.map(|app| app.config),
+    };
+    output.emit(&report, |report| {
+        match report.config {
+            Some(ref config) => {
+                println!("worktree_path = {}", config.worktree_path);
+                println!(
+                    "default_base_branch = {}",
+                    config.default_base_branch.as_deref().unwrap_or("")
+                );
+                println!("delete_mode = {}", config.delete_mode.name());
+                println!("editor = {}", config.editor.as_deref().unwrap_or(""));
+                println!("git_backend = {}", config.git_backend.name());
+            }
+            None => {
+                eprintln!("No worktree_path is set yet; run gitsy without arguments to set it up")
+            }
+        }
+        if let Some(ref worktree_root) = report.worktree_root {
+            println!("worktree_root = {}", worktree_root.display());
+        }
+        for source in &report.sources {
+            println!("source = {}", source.display());
+        }
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct InitReport {
+    shell: Shell,
+    script: String,
+}
+
+fn init(args: InitArgs, output: &Output) -> Result<()> {
+    let report = InitReport {
+        shell: args.shell,
+        script: format!("{}\n{}", args.shell.setup_hint(), args.shell.wrapper()),
+    };
+    output.emit(&report, |report| print!("{}", report.script))
+}
@@ -0,0 +1,426 @@
+use anyhow::{Context, Result};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Shared settings, committed with the repository.
+pub const REPO_CONFIG: &str = ".gitsy.toml";
+/// Personal overrides for one repository, kept out of git.
+pub const LOCAL_CONFIG: &str = ".gitsy.local.toml";
+
+/// Which config file a setting is read from or written to, from the
+/// weakest to the strongest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Global,
+    Repo,
+    Local,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 3] = [Layer::Global, Layer::Repo, Layer::Local];
+
+    /// `None` for the global layer when there is no home directory.
+    pub fn path(self, repo_root: &Path) -> Option<PathBuf> {
+        match self {
+            Layer::Global => global_config_path(),
+            Layer::Repo => Some(repo_root.join(REPO_CONFIG)),
+            Layer::Local => Some(repo_root.join(LOCAL_CONFIG)),
+        }
+    }
+}
+
+/// `$GITSY_CONFIG`, or `config.toml` in `$XDG_CONFIG_HOME/gitsy`, which
+/// defaults to `~/.config/gitsy` on every platform.
+pub fn global_config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("GITSY_CONFIG").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(config_home.join("gitsy").join("config.toml"))
+}
+
+/// The config files that exist, weakest first.
+pub fn sources(repo_root: &Path) -> Vec<PathBuf> {
+    Layer::ALL
+        .into_iter()
+        .filter_map(|layer| layer.path(repo_root))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Reads every layer and merges them: tables are merged key by key, and
+/// any other value in a stronger layer replaces the weaker one, so a list
+/// of hooks in `.gitsy.local.toml` replaces the list in `.gitsy.toml`.
+pub fn load_merged(repo_root: &Path) -> Result<toml::Table> {
+    let mut merged = toml::Table::new();
+    for path in sources(repo_root) {
+        merge(&mut merged, read(&path)?);
+    }
+    Ok(merged)
+}
+
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<toml::Table> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Changes the settings of one layer in place, creating its file if
+/// needed. Comments and layout of the other settings are kept. A new
+/// `.gitsy.local.toml` is added to `.git/info/exclude`.
+pub fn update(repo_root: &Path, layer: Layer, f: impl FnOnce(&mut toml_edit::Table)) -> Result<()> {
+    let path = layer
+        .path(repo_root)
+        .context("No home directory to keep the global config in")?;
+    let mut document = if path.exists() {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        content
+            .parse::<toml_edit::DocumentMut>()
+            .with_context(|| format!("Failed to parse {}", path.display()))?
+    } else {
+        toml_edit::DocumentMut::new()
+    };
+
+    f(document.as_table_mut());
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, document.to_string())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    if layer == Layer::Local {
+        exclude_local_config(repo_root)?;
+    }
+    Ok(())
+}
+
+fn exclude_local_config(repo_root: &Path) -> Result<()> {
+    let repo = Repository::open(repo_root)?;
+    if repo.status_should_ignore(Path::new(LOCAL_CONFIG))? {
+        return Ok(());
+    }
+
+    let exclude = common_dir(&repo).join("info").join("exclude");
+    if let Some(parent) = exclude.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&exclude)
+        .with_context(|| format!("Failed to open {}", exclude.display()))?;
+    writeln!(file, "/{}", LOCAL_CONFIG)?;
+    Ok(())
+}
+
+/// The keys for the dashboard's per-worktree actions, from the `[keys]` table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keys {
+    pub shell: char,
+    pub editor: char,
+    pub tmux: char,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Self {
+            shell: 's',
+            editor: 'e',
+            tmux: 't',
+        }
+    }
+}
+
+/// Keys the dashboard itself acts on, including navigation and `/` to
+/// filter, so `[keys]` can't have them.
+const DASHBOARD_KEYS: [char; 10] = ['c', 'd', 'f', 'j', 'k', 'o', 'q', 'r', 'x', '/'];
+
+impl Keys {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fails when an action's key is one the dashboard already uses, or the
+    /// key of another action, since the action could never be reached.
+    pub fn check(&self) -> Result<()> {
+        let keys = [
+            ("shell", self.shell),
+            ("editor", self.editor),
+            ("tmux", self.tmux),
+        ];
+        for (i, &(name, key)) in keys.iter().enumerate() {
+            if DASHBOARD_KEYS.contains(&key) {
+                return Err(anyhow::anyhow!(
+                    "keys.{} can't be '{}', which the dashboard already uses",
+                    name,
+                    key
+                ));
+            }
+            if let Some((other, _)) = keys[..i].iter().find(|(_, other)| *other == key) {
+                return Err(anyhow::anyhow!(
+                    "keys.{} and keys.{} are both '{}'",
+                    other,
+                    name,
+                    key
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Expands a leading `~` and the placeholders of `expand_template` other
+/// than the branch ones in `worktree_path`.
+pub fn expand_worktree_path(worktree_path: &str, repo_root: &Path) -> PathBuf {
+    let path = expand_template(worktree_path, repo_root, None);
+
+    if (path == "~" || path.starts_with("~/"))
+        && let Some(home) = dirs::home_dir()
+    {
+        return home.join(path.trim_start_matches('~').trim_start_matches('/'));
+    }
+
+    PathBuf::from(path)
+}
+
+/// Fills in `{repo}`, `{user}` and `{date}`, and given a branch, `{branch}`
+/// and `{branch_slug}`. Unknown placeholders are left as they are.
+pub fn expand_template(template: &str, repo_root: &Path, branch_name: Option<&str>) -> String {
+    let mut expanded = template.to_string();
+
+    // Only look things up when they are used; `{repo}` opens the repository
+    if expanded.contains("{repo}") {
+        expanded = expanded.replace("{repo}", &repo_name(repo_root));
+    }
+    if expanded.contains("{user}") {
+        let user = env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .unwrap_or_else(|_| "unknown".to_string());
+        expanded = expanded.replace("{user}", &user);
+    }
+    if expanded.contains("{date}") {
+        let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+        expanded = expanded.replace("{date}", &date);
+    }
+    if let Some(branch_name) = branch_name {
+        expanded = expanded
+            .replace("{branch_slug}", &branch_slug(branch_name))
+            .replace("{branch}", branch_name);
+    }
+
+    expanded
+}
+
+/// `feature/Add login` becomes `feature-add-login`: lowercase, with runs of
+/// anything but letters, digits, `.` and `_` turned into a single `-`.
+pub fn branch_slug(branch_name: &str) -> String {
+    let mut slug = String::new();
+    for c in branch_name.chars() {
+        if c.is_alphanumeric() || c == '.' || c == '_' {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Named after the main worktree even when gitsy runs in a linked one.
+pub fn repo_name(repo_root: &Path) -> String {
+    let main_root = Repository::open(repo_root)
+        .ok()
+        .and_then(|repo| common_dir(&repo).parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| repo_root.to_path_buf());
+
+    main_root
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The main repository's `.git` directory, which a linked worktree's own
+/// git directory lives under at `.git/worktrees/<name>`.
+pub fn common_dir(repo: &Repository) -> PathBuf {
+    let git_dir = repo.path();
+    if repo.is_worktree()
+        && let Some(common) = git_dir.parent().and_then(Path::parent)
+    {
+        return common.to_path_buf();
+    }
+    git_dir.to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Merges the layers' contents, weakest first, as `load_merged` does
+    /// with the files.
+    fn merged(layers: &[&str]) -> toml::Table {
+        let mut merged = toml::Table::new();
+        for layer in layers {
+            merge(&mut merged, toml::from_str(layer).unwrap());
+        }
+        merged
+    }
+
+    #[test]
+    fn stronger_layers_win() {
+        let global = r#"
+            worktree_path = "~/worktrees/{repo}"
+            editor = "vim"
+            [hooks]
+            pre_delete = ["./save-notes"]
+            post_create = ["echo global"]
+            [tmux]
+            enabled = true
+        "#;
+        let repo = r#"
+            worktree_path = "../wt"
+            [hooks]
+            post_create = ["npm install", "cp .env.example .env"]
+        "#;
+        let local = r#"
+            editor = "code"
+            [hooks]
+            post_create = ["npm ci"]
+        "#;
+
+        let config = merged(&[global, repo, local]);
+
+        assert_eq!(config["worktree_path"].as_str(), Some("../wt"));
+        assert_eq!(config["editor"].as_str(), Some("code"));
+        // Tables are merged key by key, lists replaced whole
+        let hooks = config["hooks"].as_table().unwrap();
+        assert_eq!(
+            hooks["pre_delete"],
+            toml::Value::Array(vec!["./save-notes".into()])
+        );
+        assert_eq!(
+            hooks["post_create"],
+            toml::Value::Array(vec!["npm ci".into()])
+        );
+        assert_eq!(config["tmux"]["enabled"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn sources_are_weakest_first() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(LOCAL_CONFIG), "").unwrap();
+        fs::write(dir.path().join(REPO_CONFIG), "").unwrap();
+
+        let sources: Vec<PathBuf> = sources(dir.path())
+            .into_iter()
+            .filter(|path| path.starts_with(dir.path()))
+            .collect();
+
+        assert_eq!(
+            sources,
+            [dir.path().join(REPO_CONFIG), dir.path().join(LOCAL_CONFIG)]
+        );
+    }
+
+    #[test]
+    fn slugs_branch_names() {
+        assert_eq!(branch_slug("feature/Add login"), "feature-add-login");
+        assert_eq!(branch_slug("fix//double--dash"), "fix-double-dash");
+        assert_eq!(branch_slug("-leading/trailing-"), "leading-trailing");
+        assert_eq!(branch_slug("release_1.2"), "release_1.2");
+        assert_eq!(branch_slug("Überarbeitung"), "überarbeitung");
+        assert_eq!(branch_slug("///"), "");
+    }
+
+    #[test]
+    fn expands_templates() {
+        let dir = TempDir::new().unwrap();
+        let repo_root = dir.path().join("shop");
+        let expand = |template| expand_template(template, &repo_root, Some("feature/Add login"));
+
+        assert_eq!(expand("{repo}/{branch_slug}"), "shop/feature-add-login");
+        assert_eq!(expand("{branch}"), "feature/Add login");
+        assert_eq!(expand("{nope}/{branch_slug}"), "{nope}/feature-add-login");
+        // Without a branch its placeholders stay
+        assert_eq!(
+            expand_template("../{repo}-{branch}", &repo_root, None),
+            "../shop-{branch}"
+        );
+
+        let date = expand_template("{date}", &repo_root, None);
+        assert_eq!(date.len(), "2024-01-31".len());
+        assert!(chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_ok());
+    }
+
+    #[test]
+    fn rejects_keys_already_taken() {
+        assert!(Keys::default().check().is_ok());
+        let keys = |shell, editor, tmux| Keys {
+            shell,
+            editor,
+            tmux,
+        };
+
+        let taken = keys('d', 'e', 't').check().unwrap_err();
+        assert_eq!(
+            taken.to_string(),
+            "keys.shell can't be 'd', which the dashboard already uses"
+        );
+        let twice = keys('s', 'e', 's').check().unwrap_err();
+        assert_eq!(twice.to_string(), "keys.shell and keys.tmux are both 's'");
+        assert!(keys('z', 'v', 'm').check().is_ok());
+    }
+
+    #[test]
+    fn update_keeps_comments_and_layout() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(REPO_CONFIG);
+        fs::write(
+            &path,
+            "# Where the team keeps worktrees\n\
+             worktree_path = \"../wt\"  # next to the repository\n\
+             \n\
+             [hooks]\n\
+             post_create = [\"npm install\"]\n",
+        )
+        .unwrap();
+
+        update(dir.path(), Layer::Repo, |table| {
+            table.insert("default_base_branch", toml_edit::value("main"));
+        })
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Where the team keeps worktrees\n\
+             worktree_path = \"../wt\"  # next to the repository\n\
+             default_base_branch = \"main\"\n\
+             \n\
+             [hooks]\n\
+             post_create = [\"npm install\"]\n"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use git2::{Oid, Repository};
+use std::{
+    iter,
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    sync::{MergeStatus, SyncStatus},
+    worktree::{Worktree, WorktreeChanges},
+};
+
+/// One row of the dashboard: a worktree and what it's up to.
+#[derive(Debug, Clone)]
+pub struct DashboardRow {
+    pub worktree: Worktree,
+    /// The repository's own working tree, which can't be removed
+    pub main: bool,
+    /// Relative to the repository root
+    pub path: PathBuf,
+    /// `None` for detached worktrees
+    pub sync: Option<SyncStatus>,
+    /// `None` for detached worktrees, the base branch, or without a
+    /// `default_base_branch`
+    pub merge: Option<MergeStatus>,
+    /// `None` when the worktree can't be inspected, e.g. its directory is gone
+    pub changes: Option<WorktreeChanges>,
+    pub last_commit: Option<LastCommit>,
+}
+
+/// The commit checked out in a worktree.
+#[derive(Debug, Clone)]
+pub struct LastCommit {
+    pub subject: String,
+    /// Committer time, in seconds since the epoch
+    pub time: i64,
+}
+
+impl LastCommit {
+    pub fn of_head(repo: &Repository, head: &str) -> Option<Self> {
+        let commit = repo.find_commit(Oid::from_str(head).ok()?).ok()?;
+        Some(Self {
+            subject: commit.summary().unwrap_or_default().to_string(),
+            time: commit.time().seconds(),
+        })
+    }
+
+    /// How long ago the commit was made, e.g. `3 days ago`.
+    pub fn age(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs() as i64);
+        format_age(now.saturating_sub(self.time))
+    }
+}
+
+fn format_age(seconds: i64) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+    const UNITS: [(i64, &str); 6] = [
+        (365 * DAY, "year"),
+        (30 * DAY, "month"),
+        (7 * DAY, "week"),
+        (DAY, "day"),
+        (HOUR, "hour"),
+        (MINUTE, "minute"),
+    ];
+
+    // Commits from the future, thanks to clock skew, are just as new
+    match UNITS.iter().find(|(unit, _)| seconds >= *unit) {
+        Some((unit, name)) => {
+            let count = seconds / unit;
+            let plural = if count == 1 { "" } else { "s" };
+            format!("{} {}{} ago", count, name, plural)
+        }
+        None => "just now".to_string(),
+    }
+}
+
+/// The ahead/behind column: arrows for the commits the branch and its
+/// upstream have that the other doesn't.
+pub fn ahead_behind(status: SyncStatus) -> String {
+    match status {
+        SyncStatus::Ahead(ahead) => format!("↑{}", ahead),
+        SyncStatus::Behind(behind) => format!("↓{}", behind),
+        SyncStatus::Diverged(ahead, behind) => format!("↑{} ↓{}", ahead, behind),
+        status => status.to_string(),
+    }
+}
+
+/// `path` relative to `base`, e.g. `../wt/feature` for a worktree next to
+/// the repository, or `.` for `base` itself.
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = path
+        .iter()
+        .zip(&base)
+        .take_while(|(path, base)| path == base)
+        .count();
+
+    let relative: PathBuf = iter::repeat_n(Component::ParentDir, base.len() - common)
+        .chain(path[common..].iter().copied())
+        .collect();
+    if relative.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_ages_at_unit_boundaries() {
+        const MINUTE: i64 = 60;
+        const HOUR: i64 = 60 * MINUTE;
+        const DAY: i64 = 24 * HOUR;
+        for (seconds, age) in [
+            (-5, "just now"),
+            (0, "just now"),
+            (MINUTE - 1, "just now"),
+            (MINUTE, "1 minute ago"),
+            (2 * MINUTE - 1, "1 minute ago"),
+            (2 * MINUTE, "2 minutes ago"),
+            (HOUR - 1, "59 minutes ago"),
+            (HOUR, "1 hour ago"),
+            (DAY - 1, "23 hours ago"),
+            (DAY, "1 day ago"),
+            (7 * DAY - 1, "6 days ago"),
+            (7 * DAY, "1 week ago"),
+            (30 * DAY - 1, "4 weeks ago"),
+            (30 * DAY, "1 month ago"),
+            (365 * DAY - 1, "12 months ago"),
+            (365 * DAY, "1 year ago"),
+            (3 * 365 * DAY, "3 years ago"),
+        ] {
+            assert_eq!(format_age(seconds), age, "{} seconds", seconds);
+        }
+    }
+
+    #[test]
+    fn shows_ahead_and_behind_as_arrows() {
+        assert_eq!(ahead_behind(SyncStatus::Ahead(2)), "↑2");
+        assert_eq!(ahead_behind(SyncStatus::Behind(1)), "↓1");
+        assert_eq!(ahead_behind(SyncStatus::Diverged(3, 4)), "↑3 ↓4");
+        assert_eq!(ahead_behind(SyncStatus::Synced), "in sync");
+        assert_eq!(ahead_behind(SyncStatus::NoUpstream), "no upstream");
+        assert_eq!(ahead_behind(SyncStatus::UpstreamGone), "upstream gone");
+    }
+
+    #[test]
+    fn makes_paths_relative() {
+        let repo = Path::new("/home/me/repo");
+        for (path, relative) in [
+            ("/home/me/repo", "."),
+            ("/home/me/repo/nested/wt", "nested/wt"),
+            ("/home/me/wt/feature", "../wt/feature"),
+            ("/home/me", ".."),
+            ("/tmp/wt/feature", "../../../tmp/wt/feature"),
+            ("/home/me/repository", "../repository"),
+        ] {
+            assert_eq!(
+                relative_path(Path::new(path), repo),
+                Path::new(relative),
+                "{}",
+                path
+            );
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+    time::Instant,
+};
+
+use crate::git_backend::{GitBackend, GitError};
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// How far a fetch has got, from git2's transfer progress.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FetchProgress {
+    pub received_objects: usize,
+    pub total_objects: usize,
+    pub indexed_deltas: usize,
+    pub total_deltas: usize,
+    pub received_bytes: usize,
+}
+
+impl fmt::Display for FetchProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.total_deltas > 0 && self.received_objects == self.total_objects {
+            write!(
+                f,
+                "resolving deltas {}/{}",
+                self.indexed_deltas, self.total_deltas
+            )
+        } else {
+            write!(
+                f,
+                "{}/{} objects, {}",
+                self.received_objects,
+                self.total_objects,
+                format_bytes(self.received_bytes)
+            )
+        }
+    }
+}
+
+/// The frame of the status line spinner for work that began at `started`.
+pub fn spinner(started: Instant) -> char {
+    SPINNER[(started.elapsed().as_millis() / 100) as usize % SPINNER.len()]
+}
+
+fn format_bytes(bytes: usize) -> String {
+    const KIB: f64 = 1024.0;
+    let bytes = bytes as f64;
+    if bytes < KIB {
+        format!("{} B", bytes)
+    } else if bytes < KIB * KIB {
+        format!("{:.1} KiB", bytes / KIB)
+    } else {
+        format!("{:.1} MiB", bytes / (KIB * KIB))
+    }
+}
+
+/// Progress of a fetch running on a background thread.
+#[derive(Debug)]
+pub enum FetchMessage {
+    Progress(FetchProgress),
+    Finished(Result<(), GitError>),
+}
+
+/// A fetch running on a background thread, so the TUI can keep drawing its
+/// progress and cancel it.
+pub struct FetchJob {
+    pub remote: String,
+    receiver: Receiver<FetchMessage>,
+    cancel: Arc<AtomicBool>,
+    progress: Option<FetchProgress>,
+    started: Instant,
+}
+
+impl FetchJob {
+    pub fn spawn(git: Box<dyn GitBackend>, remote: String) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread_cancel = Arc::clone(&cancel);
+        let thread_remote = remote.clone();
+        thread::spawn(move || {
+            let result = git.fetch(&thread_remote, &thread_cancel, &mut |progress| {
+                let _ = sender.send(FetchMessage::Progress(progress));
+            });
+            let _ = sender.send(FetchMessage::Finished(result));
+        });
+
+        Self {
+            remote,
+            receiver,
+            cancel,
+            progress: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Takes in the latest progress, and returns the result once the fetch
+    /// is done.
+    pub fn poll(&mut self) -> Option<Result<(), GitError>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(FetchMessage::Progress(progress)) => self.progress = Some(progress),
+                Ok(FetchMessage::Finished(result)) => return Some(result),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    return Some(Err(GitError::Command {
+                        command: "git fetch".to_string(),
+                        stderr: "stopped unexpectedly".to_string(),
+                    }));
+                }
+            }
+        }
+    }
+
+    /// Stops the fetch. The thread is left to wind down on its own, since
+    /// libgit2 only checks at its next progress update.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// A spinner and the progress so far, for the status line.
+    pub fn status(&self) -> String {
+        let elapsed = self.started.elapsed().as_secs();
+        match self.progress {
+            Some(progress) if progress.total_objects > 0 => format!(
+                "{} Fetching from {}: {} ({}s)",
+                spinner(self.started),
+                self.remote,
+                progress,
+                elapsed
+            ),
+            _ => format!(
+                "{} Fetching from {}... ({}s)",
+                spinner(self.started),
+                self.remote,
+                elapsed
+            ),
+        }
+    }
+}
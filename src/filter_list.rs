@@ -0,0 +1,408 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Row, Table, TableState},
+};
+
+/// The selection of a list screen, narrowed down by a fuzzy query typed after
+/// `/`. Screens keep their items and hand over the text each one is matched
+/// against; this tracks which items are shown, best match first, and which
+/// of them is selected.
+#[derive(Debug, Default)]
+pub struct FilterList {
+    labels: Vec<String>,
+    query: String,
+    /// Keys are typed into the query rather than acting on the list
+    editing: bool,
+    matches: Vec<Match>,
+    /// Index into `matches`
+    selected: usize,
+}
+
+/// An item that matches the query.
+#[derive(Debug, Clone)]
+pub struct Match {
+    /// Index of the item in the screen's own list
+    pub index: usize,
+    /// Char positions in the label that matched, for highlighting
+    pub positions: Vec<usize>,
+}
+
+impl FilterList {
+    pub fn new(labels: Vec<String>) -> Self {
+        let mut list = Self {
+            labels,
+            ..Default::default()
+        };
+        list.refilter();
+        list
+    }
+
+    /// The item under the cursor, or `None` when nothing matches.
+    pub fn selected(&self) -> Option<usize> {
+        self.matches.get(self.selected).map(|m| m.index)
+    }
+
+    /// The matching items, best first.
+    pub fn matches(&self) -> &[Match] {
+        &self.matches
+    }
+
+    /// Handles navigation and the query. Returns `false` for keys the screen
+    /// should handle itself, such as Enter, or Esc when there is no filter to
+    /// clear.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.editing {
+            match key.code {
+                KeyCode::Esc => {
+                    self.editing = false;
+                    self.set_query(String::new());
+                }
+                KeyCode::Enter => {
+                    // Falls through so Enter also picks the top match
+                    self.editing = false;
+                    return false;
+                }
+                KeyCode::Backspace if self.query.is_empty() => self.editing = false,
+                KeyCode::Backspace => {
+                    let mut query = self.query.clone();
+                    query.pop();
+                    self.set_query(query);
+                }
+                KeyCode::Char(c) => {
+                    let mut query = self.query.clone();
+                    query.push(c);
+                    self.set_query(query);
+                }
+                KeyCode::Up => self.previous(),
+                KeyCode::Down => self.next(),
+                _ => {}
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char('/') => self.editing = true,
+            KeyCode::Esc if !self.query.is_empty() => self.set_query(String::new()),
+            KeyCode::Up | KeyCode::Char('k') => self.previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.next(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + 1) % self.matches.len();
+        }
+    }
+
+    fn previous(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        } else {
+            self.selected = self.matches.len().saturating_sub(1);
+        }
+    }
+
+    fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.refilter();
+    }
+
+    /// Ranks the items against the query, keeping their own order for equal
+    /// scores and when there is no query.
+    fn refilter(&mut self) {
+        let matcher = SkimMatcherV2::default();
+        let mut scored: Vec<(i64, Match)> = self
+            .labels
+            .iter()
+            .enumerate()
+            .filter_map(|(index, label)| {
+                if self.query.is_empty() {
+                    return Some((
+                        0,
+                        Match {
+                            index,
+                            positions: Vec::new(),
+                        },
+                    ));
+                }
+                matcher
+                    .fuzzy_indices(label, &self.query)
+                    .map(|(score, positions)| (score, Match { index, positions }))
+            })
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        self.matches = scored.into_iter().map(|(_, m)| m).collect();
+        self.selected = 0;
+    }
+
+    /// Draws the matching items. `line` builds each row from the item's
+    /// index, the row's style, which marks the selection, and the label in
+    /// that style with the matched characters highlighted.
+    pub fn render(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        title: &str,
+        line: impl Fn(usize, Style, Vec<Span<'static>>) -> Line<'static>,
+    ) {
+        let mut items: Vec<ListItem> = self
+            .matches
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let style = self.row_style(i);
+                ListItem::new(line(m.index, style, self.highlighted(m, style)))
+            })
+            .collect();
+        if items.is_empty() {
+            items.push(ListItem::new(no_matches()));
+        }
+
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(self.title(title)),
+        );
+        // The list scrolls to keep the selected row visible
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    /// Draws the matching items as a table under `header`, like `render`
+    /// with `row` building each row's cells.
+    pub fn render_table(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        title: &str,
+        header: Row<'static>,
+        widths: &[Constraint],
+        row: impl Fn(usize, Style, Vec<Span<'static>>) -> Row<'static>,
+    ) {
+        let mut rows: Vec<Row> = self
+            .matches
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let style = self.row_style(i);
+                row(m.index, style, self.highlighted(m, style))
+            })
+            .collect();
+        if rows.is_empty() {
+            rows.push(Row::new([no_matches()]));
+        }
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .column_spacing(2)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(self.title(title)),
+            );
+        let mut state = TableState::default().with_selected(Some(self.selected));
+        f.render_stateful_widget(table, area, &mut state);
+    }
+
+    /// The row's style, which marks the selection.
+    fn row_style(&self, row: usize) -> Style {
+        if row == self.selected {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        }
+    }
+
+    /// The screen's title, followed by the query while there is one.
+    fn title(&self, title: &str) -> String {
+        if self.editing || !self.query.is_empty() {
+            let cursor = if self.editing { "_" } else { "" };
+            format!("{} /{}{}", title, self.query, cursor)
+        } else {
+            title.to_string()
+        }
+    }
+
+    /// The label split into runs of matched and unmatched characters.
+    fn highlighted(&self, m: &Match, style: Style) -> Vec<Span<'static>> {
+        let highlight = style.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED);
+        let mut spans = Vec::new();
+        let mut run = String::new();
+        let mut run_matched = false;
+
+        for (i, c) in self.labels[m.index].chars().enumerate() {
+            let matched = m.positions.contains(&i);
+            if matched != run_matched && !run.is_empty() {
+                let style = if run_matched { highlight } else { style };
+                spans.push(Span::styled(std::mem::take(&mut run), style));
+            }
+            run_matched = matched;
+            run.push(c);
+        }
+        if !run.is_empty() {
+            spans.push(Span::styled(
+                run,
+                if run_matched { highlight } else { style },
+            ));
+        }
+
+        spans
+    }
+}
+
+fn no_matches() -> Span<'static> {
+    Span::styled("No matches", Style::default().fg(Color::DarkGray))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(labels: &[&str]) -> FilterList {
+        FilterList::new(labels.iter().map(|label| label.to_string()).collect())
+    }
+
+    fn press(list: &mut FilterList, codes: &[KeyCode]) {
+        for &code in codes {
+            list.handle_key(KeyEvent::from(code));
+        }
+    }
+
+    fn filter(list: &mut FilterList, query: &str) {
+        press(list, &[KeyCode::Char('/')]);
+        for c in query.chars() {
+            press(list, &[KeyCode::Char(c)]);
+        }
+    }
+
+    fn indices(list: &FilterList) -> Vec<usize> {
+        list.matches().iter().map(|m| m.index).collect()
+    }
+
+    #[test]
+    fn empty_query_shows_everything_in_order() {
+        let list = list(&["main", "feature", "bugfix"]);
+
+        assert_eq!(indices(&list), [0, 1, 2]);
+        assert!(list.matches().iter().all(|m| m.positions.is_empty()));
+        assert_eq!(list.selected(), Some(0));
+        assert_eq!(list.title("Worktrees"), "Worktrees");
+    }
+
+    #[test]
+    fn ranks_best_match_first() {
+        let mut list = list(&["main", "feature/fix-bug", "bugfix"]);
+
+        filter(&mut list, "bug");
+
+        // A match at the start of the label beats one further in
+        assert_eq!(indices(&list), [2, 1]);
+        assert_eq!(list.selected(), Some(2));
+        assert_eq!(list.title("Worktrees"), "Worktrees /bug_");
+    }
+
+    #[test]
+    fn keeps_order_of_equal_matches() {
+        let mut list = list(&["fix-a", "main", "fix-b"]);
+
+        filter(&mut list, "fix");
+
+        assert_eq!(indices(&list), [0, 2]);
+    }
+
+    #[test]
+    fn highlights_matched_characters() {
+        let mut list = list(&["bugfix"]);
+        filter(&mut list, "bfx");
+        let m = &list.matches()[0];
+        assert_eq!(m.positions, [0, 3, 5]);
+
+        let style = Style::default();
+        let highlight = style.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED);
+        let spans = list.highlighted(m, style);
+
+        let runs: Vec<(&str, bool)> = spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style == highlight))
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                ("b", true),
+                ("ug", false),
+                ("f", true),
+                ("i", false),
+                ("x", true)
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_matches() {
+        let mut list = list(&["main", "feature"]);
+
+        filter(&mut list, "zzz");
+
+        assert!(list.matches().is_empty());
+        assert_eq!(list.selected(), None);
+        // Moving around an empty list is harmless
+        press(&mut list, &[KeyCode::Down, KeyCode::Up]);
+        assert_eq!(list.selected(), None);
+    }
+
+    #[test]
+    fn esc_clears_the_filter() {
+        let mut list = list(&["main", "feature", "bugfix"]);
+
+        // While typing
+        filter(&mut list, "bug");
+        press(&mut list, &[KeyCode::Esc]);
+        assert_eq!(indices(&list), [0, 1, 2]);
+        assert_eq!(list.title("Worktrees"), "Worktrees");
+
+        // After Enter kept the query
+        filter(&mut list, "bug");
+        assert!(!list.handle_key(KeyEvent::from(KeyCode::Enter)));
+        assert_eq!(list.title("Worktrees"), "Worktrees /bug");
+        assert!(list.handle_key(KeyEvent::from(KeyCode::Esc)));
+        assert_eq!(indices(&list), [0, 1, 2]);
+
+        // With no filter left, Esc is the screen's
+        assert!(!list.handle_key(KeyEvent::from(KeyCode::Esc)));
+    }
+
+    #[test]
+    fn backspace_widens_the_filter() {
+        let mut list = list(&["main", "feature"]);
+
+        filter(&mut list, "mx");
+        assert!(list.matches().is_empty());
+        press(&mut list, &[KeyCode::Backspace]);
+        assert_eq!(indices(&list), [0]);
+        // On an empty query it stops editing, leaving j and k to navigate
+        press(&mut list, &[KeyCode::Backspace, KeyCode::Backspace]);
+        assert_eq!(list.title("Worktrees"), "Worktrees");
+        press(&mut list, &[KeyCode::Char('j')]);
+        assert_eq!(list.selected(), Some(1));
+    }
+
+    #[test]
+    fn navigation_wraps_around() {
+        let mut list = list(&["main", "feature", "bugfix"]);
+
+        press(&mut list, &[KeyCode::Up]);
+        assert_eq!(list.selected(), Some(2));
+        press(&mut list, &[KeyCode::Char('j')]);
+        assert_eq!(list.selected(), Some(0));
+    }
+}
@@ -0,0 +1,1225 @@
+use git2::{
+    BranchType, Cred, CredentialType, ErrorCode, FetchOptions, FetchPrune, PushOptions,
+    RemoteCallbacks, Repository, StatusOptions, WorktreeAddOptions, WorktreeLockStatus,
+    WorktreePruneOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs, io,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{config, fetch::FetchProgress, worktree, worktree::Worktree};
+
+/// Which implementation carries out the git operations that change the
+/// repository or talk to a remote, from `git_backend` in .gitsy.toml.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitBackendKind {
+    /// libgit2, which behaves the same whatever git version and locale are
+    /// installed
+    #[default]
+    Git2,
+    /// The `git` command, for setups libgit2 doesn't support, such as SSH
+    /// settings in ~/.ssh/config or credential prompts
+    Cli,
+}
+
+impl GitBackendKind {
+    /// Stable identifier used in .gitsy.toml and JSON output.
+    pub fn name(self) -> &'static str {
+        match self {
+            GitBackendKind::Git2 => "git2",
+            GitBackendKind::Cli => "cli",
+        }
+    }
+
+    pub fn open(self, repo_root: &Path) -> Box<dyn GitBackend> {
+        let repo_root = repo_root.to_path_buf();
+        match self {
+            GitBackendKind::Git2 => Box::new(Git2Backend { repo_root }),
+            GitBackendKind::Cli => Box::new(CliBackend { repo_root }),
+        }
+    }
+}
+
+/// Why a git operation failed.
+#[derive(Debug)]
+pub enum GitError {
+    /// The `git` command couldn't be run
+    Spawn(io::Error),
+    Io(io::Error),
+    /// The `git` command failed in a way that isn't recognized below
+    Command {
+        command: String,
+        stderr: String,
+    },
+    /// libgit2 failed in a way that isn't recognized below
+    Git2(git2::Error),
+    NoSuchRemote(String),
+    NoSuchBranch(String),
+    /// A commit-ish that doesn't resolve to a commit
+    NoSuchRevision(String),
+    BranchExists(String),
+    /// The branch is checked out in another worktree
+    BranchCheckedOut(String),
+    PathExists(PathBuf),
+    /// The path is the main worktree, or not a worktree at all
+    NotLinked(PathBuf),
+    Locked(PathBuf),
+    /// The worktree has modified, staged or untracked files
+    Dirty(PathBuf),
+    /// The remote refused the credentials
+    Auth(String),
+    /// A push was rejected by the remote
+    Rejected {
+        refname: String,
+        reason: String,
+    },
+    Cancelled,
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitError::Spawn(e) => write!(f, "Failed to run git: {}", e),
+            GitError::Io(e) => write!(f, "{}", e),
+            GitError::Command { command, stderr } => {
+                write!(f, "{} failed: {}", command, stderr.trim())
+            }
+            GitError::Git2(e) => write!(f, "{}", e.message()),
+            GitError::NoSuchRemote(remote) => write!(f, "No remote named '{}'", remote),
+            GitError::NoSuchBranch(branch) => write!(f, "No branch named '{}'", branch),
+            GitError::NoSuchRevision(revision) => {
+                write!(f, "'{}' is not a branch, tag or commit", revision)
+            }
+            GitError::BranchExists(branch) => {
+                write!(f, "A branch named '{}' already exists", branch)
+            }
+            GitError::BranchCheckedOut(branch) => {
+                write!(
+                    f,
+                    "Branch '{}' is already checked out in a worktree",
+                    branch
+                )
+            }
+            GitError::PathExists(path) => write!(f, "{} already exists", path.display()),
+            GitError::NotLinked(path) => {
+                write!(f, "{} is not a linked worktree", path.display())
+            }
+            GitError::Locked(path) => write!(
+                f,
+                "The worktree at {} is locked; unlock it with git worktree unlock or force the removal",
+                path.display()
+            ),
+            GitError::Dirty(path) => write!(
+                f,
+                "The worktree at {} has modified or untracked files; force the removal to discard them",
+                path.display()
+            ),
+            GitError::Auth(remote) => write!(f, "Authentication with {} failed", remote),
+            GitError::Rejected { refname, reason } => {
+                write!(f, "The remote rejected {}: {}", refname, reason)
+            }
+            GitError::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for GitError {}
+
+impl From<git2::Error> for GitError {
+    fn from(e: git2::Error) -> Self {
+        GitError::Git2(e)
+    }
+}
+
+/// What `add_worktree` checks out in the new worktree.
+#[derive(Debug, Clone, Copy)]
+pub enum NewWorktree<'a> {
+    /// A new branch, starting at `base` or HEAD
+    Branch {
+        name: &'a str,
+        base: Option<&'a str>,
+    },
+    /// An existing local branch
+    Existing(&'a str),
+    /// A new branch tracking a remote-tracking branch like `origin/feature`
+    Tracking {
+        name: &'a str,
+        remote_branch: &'a str,
+    },
+    /// A tag or commit, with a detached HEAD
+    Detached(&'a str),
+}
+
+/// The git operations gitsy needs, besides the read-only ones like sync
+/// status that always go through libgit2.
+pub trait GitBackend: Send {
+    /// Every worktree, the main one first, as `git worktree list` has them.
+    fn list_worktrees(&self) -> Result<Vec<Worktree>, GitError>;
+
+    fn add_worktree(&self, path: &Path, new: NewWorktree) -> Result<(), GitError>;
+
+    /// Removes the worktree, or for a prunable one, git's record of it.
+    /// Without `force`, a locked worktree or one with changes is kept.
+    fn remove_worktree(&self, worktree: &Worktree, force: bool) -> Result<(), GitError>;
+
+    /// Deletes a local branch whether or not it is merged.
+    fn delete_branch(&self, branch: &str) -> Result<(), GitError>;
+
+    /// Deletes `branch` on `remote`, like `git push <remote> --delete`.
+    fn delete_remote_branch(&self, remote: &str, branch: &str) -> Result<(), GitError>;
+
+    fn remotes(&self) -> Result<Vec<String>, GitError>;
+
+    /// The remote-tracking branches of `remote`, e.g. `origin/main`, sorted.
+    fn remote_branches(&self, remote: &str) -> Result<Vec<String>, GitError>;
+
+    /// Fetches `remote` with `--prune`, handing the transfer progress to
+    /// `on_progress`. Setting `cancel` stops the fetch with
+    /// `GitError::Cancelled`.
+    fn fetch(
+        &self,
+        remote: &str,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(FetchProgress),
+    ) -> Result<(), GitError>;
+}
+
+pub struct Git2Backend {
+    repo_root: PathBuf,
+}
+
+impl Git2Backend {
+    fn open(&self) -> Result<Repository, GitError> {
+        Ok(Repository::open(&self.repo_root)?)
+    }
+
+    fn find_remote<'r>(
+        &self,
+        repo: &'r Repository,
+        remote: &str,
+    ) -> Result<git2::Remote<'r>, GitError> {
+        repo.find_remote(remote).map_err(|e| match e.code() {
+            ErrorCode::NotFound | ErrorCode::InvalidSpec => {
+                GitError::NoSuchRemote(remote.to_string())
+            }
+            _ => e.into(),
+        })
+    }
+
+    fn commit<'r>(
+        &self,
+        repo: &'r Repository,
+        revision: &str,
+    ) -> Result<git2::Commit<'r>, GitError> {
+        repo.revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| GitError::NoSuchRevision(revision.to_string()))
+    }
+
+    /// The linked worktree git keeps at `path`.
+    fn find_linked(
+        &self,
+        repo: &Repository,
+        path: &Path,
+    ) -> Result<Option<git2::Worktree>, GitError> {
+        for name in repo.worktrees()?.iter().flatten() {
+            let linked = repo.find_worktree(name)?;
+            if same_path(linked.path(), path) {
+                return Ok(Some(linked));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl GitBackend for Git2Backend {
+    fn list_worktrees(&self) -> Result<Vec<Worktree>, GitError> {
+        let repo = self.open()?;
+        let common_dir = config::common_dir(&repo);
+        let main = Repository::open(&common_dir)?;
+
+        let mut worktrees = Vec::new();
+        let mut main_worktree = match main.workdir() {
+            Some(workdir) => Worktree {
+                path: normalize(workdir),
+                ..Default::default()
+            },
+            None => Worktree {
+                path: normalize(&common_dir),
+                bare: true,
+                ..Default::default()
+            },
+        };
+        if !main_worktree.bare {
+            read_head(&main, &common_dir.join("HEAD"), &mut main_worktree);
+        }
+        worktrees.push(main_worktree);
+
+        let mut linked_worktrees = Vec::new();
+        for name in main.worktrees()?.iter().flatten() {
+            let linked = main.find_worktree(name)?;
+            let locked = matches!(linked.is_locked()?, WorktreeLockStatus::Locked(_));
+            let mut worktree = Worktree {
+                path: linked.path().to_path_buf(),
+                locked,
+                prunable: !locked && linked.validate().is_err(),
+                ..Default::default()
+            };
+            // The worktree's own HEAD, read from where git keeps it so
+            // worktrees whose directory is gone still have one
+            let head = common_dir.join("worktrees").join(name).join("HEAD");
+            read_head(&main, &head, &mut worktree);
+            linked_worktrees.push(worktree);
+        }
+        // git lists the linked worktrees by path
+        linked_worktrees.sort_by(|a, b| a.path.cmp(&b.path));
+        worktrees.extend(linked_worktrees);
+
+        Ok(worktrees)
+    }
+
+    fn add_worktree(&self, path: &Path, new: NewWorktree) -> Result<(), GitError> {
+        let repo = self.open()?;
+
+        if let NewWorktree::Branch { name, .. } | NewWorktree::Tracking { name, .. } = new
+            && repo.find_branch(name, BranchType::Local).is_ok()
+        {
+            return Err(GitError::BranchExists(name.to_string()));
+        }
+        // git accepts an empty directory, libgit2 doesn't. It is only removed
+        // once everything else checks out, right before adding the worktree
+        let empty_dir = path.exists();
+        if empty_dir && !is_empty_dir(path) {
+            return Err(GitError::PathExists(path.to_path_buf()));
+        }
+
+        let new_branch = |name: &str, base: &str| {
+            let commit = self.commit(&repo, base)?;
+            Ok::<_, GitError>(repo.branch(name, &commit, false)?)
+        };
+
+        let (mut branch, detach_at) = match new {
+            NewWorktree::Branch { name, base } => {
+                let base = base.unwrap_or("HEAD");
+                let mut branch = new_branch(name, base)?;
+                // What git's branch.autoSetupMerge does, so the branch has
+                // the same upstream whichever backend made it
+                if repo.find_branch(base, BranchType::Remote).is_ok()
+                    && auto_setup_merge(&repo)
+                    && let Err(e) = branch.set_upstream(Some(base))
+                {
+                    let _ = branch.delete();
+                    return Err(e.into());
+                }
+                (branch, None)
+            }
+            NewWorktree::Existing(name) => {
+                let branch = repo
+                    .find_branch(name, BranchType::Local)
+                    .map_err(|_| GitError::NoSuchBranch(name.to_string()))?;
+                if self
+                    .list_worktrees()?
+                    .iter()
+                    .any(|worktree| worktree.branch.as_deref() == Some(name))
+                {
+                    return Err(GitError::BranchCheckedOut(name.to_string()));
+                }
+                (branch, None)
+            }
+            NewWorktree::Tracking {
+                name,
+                remote_branch,
+            } => {
+                if repo.find_branch(remote_branch, BranchType::Remote).is_err() {
+                    return Err(GitError::NoSuchBranch(remote_branch.to_string()));
+                }
+                let mut branch = new_branch(name, remote_branch)?;
+                if let Err(e) = branch.set_upstream(Some(remote_branch)) {
+                    let _ = branch.delete();
+                    return Err(e.into());
+                }
+                (branch, None)
+            }
+            NewWorktree::Detached(target) => {
+                // libgit2 always checks out a branch in a new worktree, so
+                // this one is only there until HEAD is detached
+                let commit = self.commit(&repo, target)?;
+                let name = format!("gitsy-detach/{}", admin_name(&repo, path));
+                let branch = repo.branch(&name, &commit, false).map_err(|e| {
+                    if e.code() == ErrorCode::Exists {
+                        GitError::BranchExists(name.clone())
+                    } else {
+                        e.into()
+                    }
+                })?;
+                (branch, Some(commit.id()))
+            }
+        };
+
+        let mut options = WorktreeAddOptions::new();
+        options.reference(Some(branch.get()));
+        let added = make_room(path, empty_dir).and_then(|()| {
+            repo.worktree(&admin_name(&repo, path), path, Some(&options))
+                .map_err(GitError::from)
+        });
+        if let Err(e) = added {
+            // Don't leave behind a branch that was only made for this
+            // worktree, and put back the directory that was there
+            if !matches!(new, NewWorktree::Existing(_)) {
+                let _ = branch.delete();
+            }
+            if empty_dir && !path.exists() {
+                let _ = fs::create_dir(path);
+            }
+            return Err(e);
+        }
+
+        if let Some(commit) = detach_at {
+            let detached = Repository::open(path).and_then(|repo| repo.set_head_detached(commit));
+            branch.delete()?;
+            detached?;
+        }
+
+        Ok(())
+    }
+
+    fn remove_worktree(&self, worktree: &Worktree, force: bool) -> Result<(), GitError> {
+        if worktree.locked && !force {
+            return Err(GitError::Locked(worktree.path.clone()));
+        }
+
+        let repo = self.open()?;
+        let Some(linked) = self.find_linked(&repo, &worktree.path)? else {
+            return Err(GitError::NotLinked(worktree.path.clone()));
+        };
+
+        if worktree.prunable {
+            linked.prune(Some(WorktreePruneOptions::new().locked(force)))?;
+            return Ok(());
+        }
+
+        if !force && has_changes(&worktree.path)? {
+            return Err(GitError::Dirty(worktree.path.clone()));
+        }
+        linked.prune(Some(
+            WorktreePruneOptions::new()
+                .valid(true)
+                .locked(force)
+                .working_tree(true),
+        ))?;
+        Ok(())
+    }
+
+    fn delete_branch(&self, branch: &str) -> Result<(), GitError> {
+        let repo = self.open()?;
+        repo.find_branch(branch, BranchType::Local)
+            .map_err(|_| GitError::NoSuchBranch(branch.to_string()))?
+            .delete()?;
+        Ok(())
+    }
+
+    fn delete_remote_branch(&self, remote: &str, branch: &str) -> Result<(), GitError> {
+        let repo = self.open()?;
+        let mut git_remote = self.find_remote(&repo, remote)?;
+
+        // The push itself succeeds when the remote refuses the update; only
+        // this callback hears about it
+        let mut rejected = None;
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(credentials(repo.config()?));
+        callbacks.push_update_reference(|refname, status| {
+            if let Some(reason) = status {
+                rejected = Some(GitError::Rejected {
+                    refname: refname.to_string(),
+                    reason: reason.to_string(),
+                });
+            }
+            Ok(())
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+
+        let refspec = format!(":refs/heads/{}", branch);
+        git_remote
+            .push(&[refspec.as_str()], Some(&mut options))
+            .map_err(|e| remote_error(e, remote))?;
+        drop(options);
+        rejected.map_or(Ok(()), Err)
+    }
+
+    fn remotes(&self) -> Result<Vec<String>, GitError> {
+        let repo = self.open()?;
+        let remotes = repo.remotes()?;
+        Ok(remotes.iter().flatten().map(str::to_string).collect())
+    }
+
+    fn remote_branches(&self, remote: &str) -> Result<Vec<String>, GitError> {
+        let repo = self.open()?;
+        let prefix = format!("{}/", remote);
+
+        let mut branches = Vec::new();
+        for branch in repo.branches(Some(BranchType::Remote))? {
+            let (branch, _) = branch?;
+            // `origin/HEAD` is an alias for the remote's default branch
+            if branch.get().symbolic_target().is_some() {
+                continue;
+            }
+            if let Some(name) = branch.name()?
+                && name.starts_with(&prefix)
+            {
+                branches.push(name.to_string());
+            }
+        }
+        branches.sort();
+        Ok(branches)
+    }
+
+    fn fetch(
+        &self,
+        remote: &str,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(FetchProgress),
+    ) -> Result<(), GitError> {
+        let repo = self.open()?;
+        let mut git_remote = self.find_remote(&repo, remote)?;
+
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(credentials(repo.config()?));
+        callbacks.transfer_progress(|progress| {
+            on_progress(FetchProgress {
+                received_objects: progress.received_objects(),
+                total_objects: progress.total_objects(),
+                indexed_deltas: progress.indexed_deltas(),
+                total_deltas: progress.total_deltas(),
+                received_bytes: progress.received_bytes(),
+            });
+            !cancel.load(Ordering::Relaxed)
+        });
+        callbacks.sideband_progress(|_| !cancel.load(Ordering::Relaxed));
+
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(callbacks).prune(FetchPrune::On);
+
+        // The remote's configured refspecs, as `git fetch <remote>` uses
+        git_remote
+            .fetch::<&str>(&[], Some(&mut options), None)
+            .map_err(|e| {
+                if cancel.load(Ordering::Relaxed) {
+                    GitError::Cancelled
+                } else {
+                    remote_error(e, remote)
+                }
+            })
+    }
+}
+
+/// Credentials from ssh-agent or git's credential helpers. libgit2 asks
+/// again for as long as they are refused, so this gives up after a few
+/// tries.
+fn credentials(
+    config: git2::Config,
+) -> impl FnMut(&str, Option<&str>, CredentialType) -> Result<Cred, git2::Error> {
+    let mut attempts = 0;
+    move |url, username, allowed| {
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::new(
+                ErrorCode::Auth,
+                git2::ErrorClass::Net,
+                "authentication failed",
+            ));
+        }
+        let username = username.unwrap_or("git");
+        if allowed.contains(CredentialType::SSH_KEY) {
+            Cred::ssh_key_from_agent(username)
+        } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            Cred::credential_helper(&config, url, Some(username))
+        } else if allowed.contains(CredentialType::USERNAME) {
+            Cred::username(username)
+        } else {
+            Cred::default()
+        }
+    }
+}
+
+/// Whether a new branch tracks the remote-tracking branch it starts from,
+/// which git does unless `branch.autoSetupMerge` is off.
+fn auto_setup_merge(repo: &Repository) -> bool {
+    match repo
+        .config()
+        .and_then(|config| config.get_string("branch.autoSetupMerge"))
+    {
+        Ok(value) => value != "false",
+        Err(_) => true,
+    }
+}
+
+fn remote_error(e: git2::Error, remote: &str) -> GitError {
+    if e.code() == ErrorCode::Auth {
+        GitError::Auth(remote.to_string())
+    } else {
+        e.into()
+    }
+}
+
+/// Fills in the branch and commit of a worktree from its `HEAD` file.
+fn read_head(repo: &Repository, head_file: &Path, worktree: &mut Worktree) {
+    let Ok(content) = fs::read_to_string(head_file) else {
+        return;
+    };
+    let content = content.trim();
+    match content.strip_prefix("ref: ") {
+        Some(refname) => {
+            let branch = refname.strip_prefix("refs/heads/").unwrap_or(refname);
+            worktree.branch = Some(branch.to_string());
+            // An unborn branch has no commit yet
+            worktree.head = repo.refname_to_id(refname).ok().map(|id| id.to_string());
+        }
+        None => {
+            worktree.detached = true;
+            worktree.head = Some(content.to_string());
+        }
+    }
+}
+
+/// The name of the directory under `.git/worktrees` for a worktree at
+/// `path`: its directory name, numbered when that is taken, as git does.
+fn admin_name(repo: &Repository, path: &Path) -> String {
+    let base = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "worktree".to_string());
+    let admin = config::common_dir(repo).join("worktrees");
+
+    let mut name = base.clone();
+    let mut suffix = 1;
+    while admin.join(&name).exists() {
+        name = format!("{}{}", base, suffix);
+        suffix += 1;
+    }
+    name
+}
+
+/// Whether `git worktree remove` would refuse to remove the worktree
+/// without `--force`.
+fn has_changes(path: &Path) -> Result<bool, GitError> {
+    let repo = Repository::open(path)?;
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).include_ignored(false);
+    Ok(!repo.statuses(Some(&mut options))?.is_empty())
+}
+
+/// Drops the trailing slash libgit2 puts on directories.
+fn normalize(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+}
+
+/// Clears the way for libgit2 to create the worktree directory at `path`:
+/// removes the empty directory already there and creates its parents.
+fn make_room(path: &Path, empty_dir: bool) -> Result<(), GitError> {
+    if empty_dir {
+        fs::remove_dir(path).map_err(|_| GitError::PathExists(path.to_path_buf()))?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(GitError::Io)?;
+    }
+    Ok(())
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| normalize(path));
+    canonical(a) == canonical(b)
+}
+
+pub struct CliBackend {
+    repo_root: PathBuf,
+}
+
+impl CliBackend {
+    /// `git` in the repository, with git's messages in English so failures
+    /// can be told apart.
+    fn git(&self) -> Command {
+        let mut command = Command::new("git");
+        command.current_dir(&self.repo_root).env("LC_ALL", "C");
+        command
+    }
+
+    /// Runs `git <args>` and returns its stdout.
+    fn run(&self, args: &[&str]) -> Result<String, GitError> {
+        let output = self.git().args(args).output().map_err(GitError::Spawn)?;
+
+        if !output.status.success() {
+            return Err(GitError::Command {
+                command: format!("git {}", args[..args.len().min(2)].join(" ")),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// The directory in `.git/worktrees` where git keeps its record of the
+    /// worktree at `path`, found by the `gitdir` file pointing back at it.
+    fn admin_dir(&self, path: &Path) -> Result<Option<PathBuf>, GitError> {
+        let common_dir = self.run(&["rev-parse", "--git-common-dir"])?;
+        let Ok(entries) = fs::read_dir(self.repo_root.join(common_dir.trim()).join("worktrees"))
+        else {
+            return Ok(None);
+        };
+
+        for entry in entries.flatten() {
+            let Ok(gitdir) = fs::read_to_string(entry.path().join("gitdir")) else {
+                continue;
+            };
+            if Path::new(gitdir.trim())
+                .parent()
+                .is_some_and(|dir| same_path(dir, path))
+            {
+                return Ok(Some(entry.path()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl GitBackend for CliBackend {
+    fn list_worktrees(&self) -> Result<Vec<Worktree>, GitError> {
+        let stdout = self.run(&["worktree", "list", "--porcelain"])?;
+        Ok(worktree::parse_porcelain(&stdout))
+    }
+
+    fn add_worktree(&self, path: &Path, new: NewWorktree) -> Result<(), GitError> {
+        let path_arg = path.to_string_lossy();
+        let mut args = vec!["worktree", "add"];
+        match new {
+            NewWorktree::Branch { name, base } => {
+                args.extend(["-b", name, &path_arg]);
+                args.extend(base);
+            }
+            NewWorktree::Existing(name) => args.extend([&path_arg, name]),
+            NewWorktree::Tracking {
+                name,
+                remote_branch,
+            } => args.extend(["--track", "-b", name, &path_arg, remote_branch]),
+            NewWorktree::Detached(target) => args.extend(["--detach", &path_arg, target]),
+        }
+
+        self.run(&args).map(drop).map_err(|e| {
+            let GitError::Command { ref stderr, .. } = e else {
+                return e;
+            };
+            match new {
+                NewWorktree::Branch { name, .. } | NewWorktree::Tracking { name, .. }
+                    if stderr.contains("a branch named") =>
+                {
+                    GitError::BranchExists(name.to_string())
+                }
+                NewWorktree::Existing(name) if stderr.contains("is already") => {
+                    GitError::BranchCheckedOut(name.to_string())
+                }
+                NewWorktree::Branch {
+                    base: Some(revision),
+                    ..
+                }
+                | NewWorktree::Tracking {
+                    remote_branch: revision,
+                    ..
+                }
+                | NewWorktree::Existing(revision)
+                | NewWorktree::Detached(revision)
+                    if stderr.contains("invalid reference") =>
+                {
+                    GitError::NoSuchRevision(revision.to_string())
+                }
+                _ if stderr.contains("already exists") => GitError::PathExists(path.to_path_buf()),
+                _ => e,
+            }
+        })
+    }
+
+    fn remove_worktree(&self, worktree: &Worktree, force: bool) -> Result<(), GitError> {
+        if worktree.locked && !force {
+            return Err(GitError::Locked(worktree.path.clone()));
+        }
+
+        // `git worktree remove` refuses a missing directory, and `git
+        // worktree prune` would drop the record of every such worktree, not
+        // just this one
+        if worktree.prunable {
+            let admin_dir = self
+                .admin_dir(&worktree.path)?
+                .ok_or_else(|| GitError::NotLinked(worktree.path.clone()))?;
+            return fs::remove_dir_all(admin_dir).map_err(GitError::Io);
+        }
+
+        let path = worktree.path.to_string_lossy();
+        let mut args = vec!["worktree", "remove"];
+        if force {
+            // Twice to also remove a locked worktree
+            args.extend(["--force", "--force"]);
+        }
+        args.push(&path);
+
+        self.run(&args).map(drop).map_err(|e| match e {
+            GitError::Command { ref stderr, .. } if stderr.contains("is locked") => {
+                GitError::Locked(worktree.path.clone())
+            }
+            GitError::Command { ref stderr, .. } if stderr.contains("modified or untracked") => {
+                GitError::Dirty(worktree.path.clone())
+            }
+            e => e,
+        })
+    }
+
+    fn delete_branch(&self, branch: &str) -> Result<(), GitError> {
+        self.run(&["branch", "-D", branch])
+            .map(drop)
+            .map_err(|e| match e {
+                GitError::Command { ref stderr, .. } if stderr.contains("not found") => {
+                    GitError::NoSuchBranch(branch.to_string())
+                }
+                e => e,
+            })
+    }
+
+    fn delete_remote_branch(&self, remote: &str, branch: &str) -> Result<(), GitError> {
+        self.run(&["push", remote, "--delete", branch])
+            .map(drop)
+            .map_err(|e| match e {
+                GitError::Command { ref stderr, .. } if stderr.contains("[rejected]") => {
+                    GitError::Rejected {
+                        refname: format!("refs/heads/{}", branch),
+                        reason: stderr.trim().to_string(),
+                    }
+                }
+                e => cli_remote_error(e, remote),
+            })
+    }
+
+    fn remotes(&self) -> Result<Vec<String>, GitError> {
+        let stdout = self.run(&["remote"])?;
+        Ok(stdout
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn remote_branches(&self, remote: &str) -> Result<Vec<String>, GitError> {
+        let stdout = self.run(&["branch", "-r", "--format=%(refname:short)"])?;
+        let prefix = format!("{}/", remote);
+        Ok(stdout
+            .lines()
+            // `origin/HEAD` shows up as `origin`
+            .filter(|line| *line != remote && !line.ends_with("/HEAD"))
+            .filter(|line| line.starts_with(&prefix))
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn fetch(
+        &self,
+        remote: &str,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(FetchProgress),
+    ) -> Result<(), GitError> {
+        if !self.remotes()?.iter().any(|name| name == remote) {
+            return Err(GitError::NoSuchRemote(remote.to_string()));
+        }
+
+        let mut child = self
+            .git()
+            .args(["fetch", "--prune", "--progress", remote])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(GitError::Spawn)?;
+
+        // git redraws its progress with `\r`, so split on that too
+        let (sender, receiver) = mpsc::channel();
+        let stderr = child.stderr.take();
+        let reader = thread::spawn(move || {
+            let Some(stderr) = stderr else {
+                return;
+            };
+            let mut line = Vec::new();
+            for byte in BufReader::new(stderr).bytes().map_while(Result::ok) {
+                if byte == b'\r' || byte == b'\n' {
+                    let _ = sender.send(String::from_utf8_lossy(&line).into_owned());
+                    line.clear();
+                } else {
+                    line.push(byte);
+                }
+            }
+        });
+
+        let mut progress = FetchProgress::default();
+        let mut messages = Vec::new();
+        let mut take_lines = |progress: &mut FetchProgress, messages: &mut Vec<String>| {
+            for line in receiver.try_iter() {
+                if parse_progress(&line, progress) {
+                    on_progress(*progress);
+                } else if !line.trim().is_empty() {
+                    messages.push(line);
+                }
+            }
+        };
+
+        let status = loop {
+            take_lines(&mut progress, &mut messages);
+            if cancel.load(Ordering::Relaxed) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(GitError::Cancelled);
+            }
+            if let Some(status) = child.try_wait().map_err(GitError::Spawn)? {
+                break status;
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+        let _ = reader.join();
+        take_lines(&mut progress, &mut messages);
+
+        if !status.success() {
+            return Err(cli_remote_error(
+                GitError::Command {
+                    command: "git fetch".to_string(),
+                    stderr: messages.join("\n"),
+                },
+                remote,
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn cli_remote_error(e: GitError, remote: &str) -> GitError {
+    match e {
+        GitError::Command { ref stderr, .. }
+            if stderr.contains("Authentication failed") || stderr.contains("Permission denied") =>
+        {
+            GitError::Auth(remote.to_string())
+        }
+        e => e,
+    }
+}
+
+/// Reads `Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s` and
+/// `Resolving deltas:  50% (5/10)` lines of `git fetch --progress` into
+/// `progress`. Returns false for any other line.
+fn parse_progress(line: &str, progress: &mut FetchProgress) -> bool {
+    let Some((stage, rest)) = line.split_once(':') else {
+        return false;
+    };
+    let Some((counts, after)) = rest
+        .split_once('(')
+        .and_then(|(_, counts)| counts.split_once(')'))
+    else {
+        return false;
+    };
+    let Some((done, total)) = counts
+        .split_once('/')
+        .and_then(|(done, total)| Some((done.parse().ok()?, total.parse().ok()?)))
+    else {
+        return false;
+    };
+
+    match stage {
+        "Receiving objects" => {
+            progress.received_objects = done;
+            progress.total_objects = total;
+            if let Some(bytes) = after
+                .trim_start_matches(',')
+                .split('|')
+                .next()
+                .and_then(parse_size)
+            {
+                progress.received_bytes = bytes;
+            }
+        }
+        "Resolving deltas" => {
+            // Every object has arrived by now
+            progress.received_objects = progress.total_objects;
+            progress.indexed_deltas = done;
+            progress.total_deltas = total;
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// `1.20 MiB` in bytes.
+fn parse_size(size: &str) -> Option<usize> {
+    let (number, unit) = size.trim().split_once(' ')?;
+    let number: f64 = number.parse().ok()?;
+    let factor = match unit {
+        "bytes" | "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * factor) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{RepositoryInitOptions, Signature};
+    use tempfile::TempDir;
+
+    /// A repository with one commit on `main`, fetched from a bare `origin`.
+    fn repository() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let origin = root.join("origin.git");
+        Repository::init_opts(
+            &origin,
+            RepositoryInitOptions::new().bare(true).initial_head("main"),
+        )
+        .unwrap();
+        let repo = Repository::init_opts(
+            root.join("repo"),
+            RepositoryInitOptions::new().initial_head("main"),
+        )
+        .unwrap();
+
+        let signature = Signature::now("Gitsy", "gitsy@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial", &tree, &[])
+            .unwrap();
+        let mut remote = repo.remote("origin", origin.to_str().unwrap()).unwrap();
+        remote
+            .push(&["refs/heads/main:refs/heads/main"], None)
+            .unwrap();
+        remote.fetch::<&str>(&[], None, None).unwrap();
+
+        (dir, root)
+    }
+
+    fn upstream(repo_root: &Path, branch: &str) -> Option<String> {
+        let repo = Repository::open(repo_root).unwrap();
+        let branch = repo.find_branch(branch, BranchType::Local).unwrap();
+        let upstream = branch.upstream().ok()?;
+        upstream.name().unwrap().map(str::to_string)
+    }
+
+    #[test]
+    fn parses_fetch_progress() {
+        let mut progress = FetchProgress::default();
+
+        assert!(parse_progress(
+            "Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s",
+            &mut progress
+        ));
+        assert_eq!(progress.received_objects, 450);
+        assert_eq!(progress.total_objects, 1000);
+        assert_eq!(progress.received_bytes, (1.2 * 1024.0 * 1024.0) as usize);
+
+        assert!(parse_progress(
+            "Resolving deltas:  50% (5/10)",
+            &mut progress
+        ));
+        assert_eq!(progress.received_objects, 1000);
+        assert_eq!(progress.indexed_deltas, 5);
+        assert_eq!(progress.total_deltas, 10);
+        // The byte count is left alone by lines without one
+        assert_eq!(progress.received_bytes, (1.2 * 1024.0 * 1024.0) as usize);
+    }
+
+    #[test]
+    fn ignores_other_fetch_output() {
+        let mut progress = FetchProgress::default();
+        for line in [
+            "",
+            "From ../origin",
+            " * [new branch]      feature    -> origin/feature",
+            "remote: Counting objects: 100% (3/3), done.",
+            "Receiving objects: (abc/def)",
+            "Receiving objects: 100% (3/3",
+        ] {
+            assert!(!parse_progress(line, &mut progress), "{:?}", line);
+        }
+        assert_eq!(progress.total_objects, 0);
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512 bytes"), Some(512));
+        assert_eq!(parse_size(" 2.00 KiB "), Some(2048));
+        assert_eq!(parse_size("1.50 MiB"), Some(1024 * 1024 * 3 / 2));
+        assert_eq!(parse_size("1 GiB"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_size("3 PiB"), None);
+        assert_eq!(parse_size("fast"), None);
+        assert_eq!(parse_size("many MiB"), None);
+    }
+
+    #[test]
+    fn new_branch_from_remote_branch_tracks_it_with_either_backend() {
+        for kind in [GitBackendKind::Git2, GitBackendKind::Cli] {
+            let (_dir, root) = repository();
+            let repo_root = root.join("repo");
+            let backend = kind.open(&repo_root);
+
+            let new = |name| NewWorktree::Branch {
+                name,
+                base: Some("origin/main"),
+            };
+            backend
+                .add_worktree(&root.join("from-remote"), new("from-remote"))
+                .unwrap();
+            let local = NewWorktree::Branch {
+                name: "from-local",
+                base: Some("main"),
+            };
+            backend
+                .add_worktree(&root.join("from-local"), local)
+                .unwrap();
+
+            assert_eq!(
+                upstream(&repo_root, "from-remote").as_deref(),
+                Some("origin/main"),
+                "{}",
+                kind.name()
+            );
+            assert_eq!(upstream(&repo_root, "from-local"), None, "{}", kind.name());
+        }
+    }
+
+    #[test]
+    fn failed_add_leaves_no_branch() {
+        let (_dir, root) = repository();
+        let repo_root = root.join("repo");
+        let backend = GitBackendKind::Git2.open(&repo_root);
+        // Where git would keep its record of the worktree
+        fs::write(repo_root.join(".git/worktrees"), "").unwrap();
+        let path = root.join("feature");
+
+        for new in [
+            NewWorktree::Branch {
+                name: "feature",
+                base: None,
+            },
+            NewWorktree::Detached("main"),
+        ] {
+            assert!(backend.add_worktree(&path, new).is_err());
+        }
+
+        let repo = Repository::open(&repo_root).unwrap();
+        let branches: Vec<String> = repo
+            .branches(Some(BranchType::Local))
+            .unwrap()
+            .map(|branch| branch.unwrap().0.name().unwrap().unwrap().to_string())
+            .collect();
+        assert_eq!(branches, ["main"]);
+    }
+
+    #[test]
+    fn failed_add_keeps_empty_directory() {
+        let (_dir, root) = repository();
+        let repo_root = root.join("repo");
+        let backend = GitBackendKind::Git2.open(&repo_root);
+        let path = root.join("feature");
+        fs::create_dir(&path).unwrap();
+
+        assert!(matches!(
+            backend.add_worktree(&path, NewWorktree::Detached("nowhere")),
+            Err(GitError::NoSuchRevision(_))
+        ));
+        assert!(path.is_dir());
+
+        // Failing once the directory is out of the way puts it back
+        fs::write(repo_root.join(".git/worktrees"), "").unwrap();
+        assert!(
+            backend
+                .add_worktree(&path, NewWorktree::Detached("main"))
+                .is_err()
+        );
+        assert!(path.is_dir());
+    }
+
+    #[test]
+    fn detached_add_keeps_existing_branch() {
+        let (_dir, root) = repository();
+        let repo_root = root.join("repo");
+        let backend = GitBackendKind::Git2.open(&repo_root);
+        let repo = Repository::open(&repo_root).unwrap();
+        let main = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("gitsy-detach/v1", &main, false).unwrap();
+
+        assert!(matches!(
+            backend.add_worktree(&root.join("v1"), NewWorktree::Detached("main")),
+            Err(GitError::BranchExists(name)) if name == "gitsy-detach/v1"
+        ));
+        assert!(
+            repo.find_branch("gitsy-detach/v1", BranchType::Local)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn removing_prunable_worktree_leaves_the_others() {
+        let (_dir, root) = repository();
+        let repo_root = root.join("repo");
+        let backend = GitBackendKind::Cli.open(&repo_root);
+        for name in ["one", "two"] {
+            let path = root.join(name);
+            backend
+                .add_worktree(&path, NewWorktree::Branch { name, base: None })
+                .unwrap();
+            fs::remove_dir_all(&path).unwrap();
+        }
+        let prunable = |backend: &dyn GitBackend| -> Vec<PathBuf> {
+            backend
+                .list_worktrees()
+                .unwrap()
+                .into_iter()
+                .filter(|worktree| worktree.prunable)
+                .map(|worktree| worktree.path)
+                .collect()
+        };
+        let one = backend
+            .list_worktrees()
+            .unwrap()
+            .into_iter()
+            .find(|worktree| worktree.path == root.join("one"))
+            .unwrap();
+
+        backend.remove_worktree(&one, false).unwrap();
+
+        assert_eq!(prunable(backend.as_ref()), [root.join("two")]);
+    }
+
+    #[test]
+    fn removing_main_worktree_is_refused() {
+        let (_dir, root) = repository();
+        let repo_root = root.join("repo");
+        let backend = GitBackendKind::Git2.open(&repo_root);
+        let main = Worktree {
+            path: repo_root.clone(),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            backend.remove_worktree(&main, false),
+            Err(GitError::NotLinked(path)) if path == repo_root
+        ));
+    }
+}
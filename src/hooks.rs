@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+/// Shell commands from the `[hooks]` table of .gitsy.toml.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Hooks {
+    /// Run in the new worktree once it has been created
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_create: Vec<String>,
+    /// Run in the worktree before it is removed; a failure cancels the removal
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_delete: Vec<String>,
+    /// Run in the repository root after the worktree is gone
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_delete: Vec<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.post_create.is_empty() && self.pre_delete.is_empty() && self.post_delete.is_empty()
+    }
+
+    pub fn commands(&self, event: HookEvent) -> &[String] {
+        match event {
+            HookEvent::PostCreate => &self.post_create,
+            HookEvent::PreDelete => &self.pre_delete,
+            HookEvent::PostDelete => &self.post_delete,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PostCreate,
+    PreDelete,
+    PostDelete,
+}
+
+impl HookEvent {
+    /// The key in `[hooks]`, also exported to hooks as `GITSY_HOOK`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::PostCreate => "post_create",
+            HookEvent::PreDelete => "pre_delete",
+            HookEvent::PostDelete => "post_delete",
+        }
+    }
+}
+
+/// The worktree a hook runs for, exported to it as `GITSY_BRANCH`,
+/// `GITSY_WORKTREE_PATH` and `GITSY_REPO_ROOT`.
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub branch: String,
+    pub worktree_path: PathBuf,
+    pub repo_root: PathBuf,
+}
+
+/// Progress of hooks running on a background thread.
+#[derive(Debug)]
+pub enum HookMessage {
+    Output(String),
+    Finished(Result<(), String>),
+}
+
+impl HookContext {
+    /// Runs `commands` one after another with `sh -c`, stopping at the first
+    /// that fails. Lines of stdout and stderr are handed to `on_output` as
+    /// they are printed.
+    pub fn run(
+        &self,
+        event: HookEvent,
+        commands: &[String],
+        on_output: &mut dyn FnMut(String),
+    ) -> Result<()> {
+        // post_delete runs after the worktree directory is gone
+        let dir = if self.worktree_path.is_dir() {
+            &self.worktree_path
+        } else {
+            &self.repo_root
+        };
+
+        for command in commands {
+            on_output(format!("[{}] $ {}", event.name(), command));
+
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .current_dir(dir)
+                .env("GITSY_HOOK", event.name())
+                .env("GITSY_BRANCH", &self.branch)
+                .env("GITSY_WORKTREE_PATH", &self.worktree_path)
+                .env("GITSY_REPO_ROOT", &self.repo_root)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .with_context(|| format!("Failed to run {} hook '{}'", event.name(), command))?;
+
+            // Read both pipes at once so neither can fill up and stall the hook
+            let (sender, receiver) = mpsc::channel();
+            let pipes: [Option<Box<dyn Read + Send>>; 2] = [
+                child.stdout.take().map(|pipe| Box::new(pipe) as _),
+                child.stderr.take().map(|pipe| Box::new(pipe) as _),
+            ];
+            for pipe in pipes.into_iter().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            for line in receiver {
+                on_output(line);
+            }
+
+            let status = child.wait().context("Failed to wait for hook")?;
+            if !status.success() {
+                return Err(anyhow::anyhow!(
+                    "{} hook '{}' failed ({})",
+                    event.name(),
+                    command,
+                    status
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs the hooks on a background thread so the TUI can keep drawing
+    /// their output. The last message is always `Finished`.
+    pub fn spawn(self, event: HookEvent, commands: Vec<String>) -> Receiver<HookMessage> {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let result = self.run(event, &commands, &mut |line| {
+                let _ = sender.send(HookMessage::Output(line));
+            });
+            let _ = sender.send(HookMessage::Finished(result.map_err(|e| e.to_string())));
+        });
+
+        receiver
+    }
+}
@@ -0,0 +1,2742 @@
+mod branch_name;
+mod cli;
+mod config;
+mod dashboard;
+mod fetch;
+mod filter_list;
+mod git_backend;
+mod hooks;
+mod prune;
+mod shared_files;
+mod shell;
+mod sync;
+mod tmux;
+mod worktree;
+
+use anyhow::{Context, Result};
+use branch_name::{BranchNameCheck, Checkout};
+use clap::Parser;
+use cli::Cli;
+use config::{Keys, Layer};
+use crossterm::{
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use dashboard::{DashboardRow, LastCommit};
+use fetch::{FetchJob, spinner};
+use filter_list::FilterList;
+use git_backend::{GitBackend, GitBackendKind, GitError, NewWorktree};
+use git2::{BranchType, Repository};
+use hooks::{HookContext, HookEvent, HookMessage, Hooks};
+use prune::{PruneCandidate, PruneReason, PruneResult};
+use ratatui::{
+    Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Row},
+};
+use serde::{Deserialize, Serialize};
+use shared_files::{ConflictPolicy, SharedFile};
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use sync::{MergeStatus, SyncStatus};
+use tmux::TmuxConfig;
+use worktree::{Worktree, WorktreeChanges};
+
+/// The merged settings of every config layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitsyConfig {
+    /// Where worktrees are created; may start with `~` and contain `{repo}`
+    worktree_path: String,
+    /// A worktree's directory inside `worktree_path`, e.g.
+    /// `{repo}/{branch_slug}`
+    #[serde(default = "default_worktree_dir_template")]
+    worktree_dir_template: String,
+    #[serde(default)]
+    default_base_branch: Option<String>,
+    /// What deleting a worktree removes unless chosen otherwise
+    #[serde(default)]
+    delete_mode: DeleteMode,
+    /// Globs of untracked files to copy from the repository root into new
+    /// worktrees, e.g. `.env.local`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    copy: Vec<String>,
+    /// Like `copy`, but symlinked so every worktree shares one file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    symlink: Vec<String>,
+    /// What to do when a file to copy or symlink already exists in the worktree
+    #[serde(default)]
+    file_conflict: ConflictPolicy,
+    /// Command to open a worktree with, run in it with `.` appended;
+    /// `$VISUAL` and then `$EDITOR` are used when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    editor: Option<String>,
+    /// Whether git operations go through libgit2 or the `git` command
+    #[serde(default)]
+    git_backend: GitBackendKind,
+    // Tables have to come last for the config to serialize
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    hooks: Hooks,
+    #[serde(default, skip_serializing_if = "TmuxConfig::is_empty")]
+    tmux: TmuxConfig,
+    #[serde(default, skip_serializing_if = "Keys::is_default")]
+    keys: Keys,
+}
+
+impl Default for GitsyConfig {
+    fn default() -> Self {
+        Self {
+            worktree_path: String::new(),
+            worktree_dir_template: default_worktree_dir_template(),
+            default_base_branch: None,
+            delete_mode: DeleteMode::default(),
+            copy: Vec::new(),
+            symlink: Vec::new(),
+            file_conflict: ConflictPolicy::default(),
+            editor: None,
+            git_backend: GitBackendKind::default(),
+            hooks: Hooks::default(),
+            tmux: TmuxConfig::default(),
+            keys: Keys::default(),
+        }
+    }
+}
+
+fn default_worktree_dir_template() -> String {
+    "{branch}".to_string()
+}
+
+/// How much of a branch goes away when its worktree is deleted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+enum DeleteMode {
+    /// Remove the worktree and keep the branch
+    #[default]
+    Worktree,
+    /// Also delete the local branch
+    LocalBranch,
+    /// Also delete the local branch and its upstream on the remote
+    RemoteBranch,
+}
+
+impl DeleteMode {
+    const ALL: [DeleteMode; 3] = [
+        DeleteMode::Worktree,
+        DeleteMode::LocalBranch,
+        DeleteMode::RemoteBranch,
+    ];
+
+    fn next(self) -> Self {
+        match self {
+            DeleteMode::Worktree => DeleteMode::LocalBranch,
+            DeleteMode::LocalBranch => DeleteMode::RemoteBranch,
+            DeleteMode::RemoteBranch => DeleteMode::Worktree,
+        }
+    }
+
+    fn deletes_branch(self) -> bool {
+        self != DeleteMode::Worktree
+    }
+
+    /// Stable identifier used in .gitsy.toml and JSON output.
+    fn name(self) -> &'static str {
+        match self {
+            DeleteMode::Worktree => "worktree",
+            DeleteMode::LocalBranch => "local-branch",
+            DeleteMode::RemoteBranch => "remote-branch",
+        }
+    }
+}
+
+impl fmt::Display for DeleteMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeleteMode::Worktree => write!(f, "worktree only"),
+            DeleteMode::LocalBranch => write!(f, "worktree + local branch"),
+            DeleteMode::RemoteBranch => write!(f, "worktree + local + remote branch"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Screen {
+    Dashboard,
+    CreateBranch,
+    SelectBaseBranchOption,
+    SelectRemote,
+    SelectRemoteBranch,
+    ConfirmDelete,
+    Cleanup,
+    ConfirmCleanup,
+    CleanupSummary,
+}
+
+/// Hooks running in the background, and what to do once they succeed.
+struct HookJob {
+    event: HookEvent,
+    /// Kept so `post_delete` still knows where the worktree was
+    worktree: Worktree,
+    receiver: Receiver<HookMessage>,
+    /// For `pre_delete`, the removal to carry out afterwards
+    then_delete: Option<(DeleteMode, bool)>,
+}
+
+/// Worktree removal running on a background thread, since deleting a
+/// remote branch waits on the network and a cleanup can take a while.
+struct RemovalJob {
+    receiver: Receiver<RemovalMessage>,
+    /// What the status line says is being removed
+    description: String,
+    started: Instant,
+}
+
+impl RemovalJob {
+    /// A spinner and the description, for the status line.
+    fn status(&self) -> String {
+        format!(
+            "{} {}... ({}s)",
+            spinner(self.started),
+            self.description,
+            self.started.elapsed().as_secs()
+        )
+    }
+}
+
+/// Progress of a removal running on a background thread.
+enum RemovalMessage {
+    /// Output of the hooks a cleanup runs
+    Output(String),
+    Finished(Removed),
+}
+
+/// What a finished `RemovalJob` did.
+enum Removed {
+    /// The worktree from the confirm delete screen, and the remote branch
+    /// deleted with it
+    Worktree {
+        worktree: Worktree,
+        mode: DeleteMode,
+        result: Result<Option<String>>,
+    },
+    /// The worktrees marked on the cleanup screen
+    Batch(Vec<PruneResult>),
+}
+
+/// What `delete_with_hooks` did besides removing the worktree.
+struct DeleteOutcome {
+    remote_branch: Option<String>,
+    /// Why the `post_delete` hooks failed, if they did; the worktree is gone
+    /// either way
+    hook_error: Option<String>,
+}
+
+/// The state of the TUI, driven by key events from `run_app`.
+pub struct App {
+    screen: Screen,
+    input: String,
+    cursor_position: usize,
+    repo_root: PathBuf,
+    config: GitsyConfig,
+    /// Every worktree of the repository, the main one first
+    dashboard: Vec<DashboardRow>,
+    dashboard_list: FilterList,
+    /// The worktree on the confirm screen, an index into `dashboard`
+    selected_worktree: usize,
+    message: Option<String>,
+    delete_mode: DeleteMode,
+    /// `None` when the worktree to delete has a detached HEAD
+    branch_sync_status: Option<SyncStatus>,
+    /// Whether a detached worktree's HEAD is on some branch or tag
+    head_referenced: bool,
+    branch_changes: Option<WorktreeChanges>,
+    branch_merge_status: Option<MergeStatus>,
+    remote_branches: Vec<String>,
+    remote_branch_list: FilterList,
+    base_branch: Option<String>,
+    /// The create screen checks out a tag or commit instead of making a
+    /// branch
+    detach: bool,
+    /// Feedback on the branch name typed on the create screen
+    branch_name_check: Option<BranchNameCheck>,
+    remotes: Vec<String>,
+    remote_list: FilterList,
+    base_branch_options: Vec<String>,
+    base_option_list: FilterList,
+    prune_candidates: Vec<PruneCandidate>,
+    /// Parallel to `prune_candidates`: whether each one is marked for removal
+    prune_marked: Vec<bool>,
+    candidate_list: FilterList,
+    prune_results: Vec<PruneResult>,
+    /// Output of the most recent hooks, shown in the log pane
+    log: Vec<String>,
+    hook_job: Option<HookJob>,
+    removal_job: Option<RemovalJob>,
+    /// The fetch started from the select remote screen or the dashboard
+    fetch_job: Option<FetchJob>,
+    /// The remotes the dashboard has yet to fetch from once `fetch_job` is
+    /// done
+    pending_fetches: Vec<String>,
+    /// The worktree made by the last successful create, offered for
+    /// switching
+    created_worktree: Option<Worktree>,
+    /// The worktree to hand to the shell once the TUI exits
+    chosen_worktree: Option<PathBuf>,
+    /// A program to run in the foreground with the TUI suspended, and its
+    /// name for error messages
+    launch: Option<(String, Command)>,
+}
+
+impl App {
+    pub fn new(repo_root: PathBuf, config: GitsyConfig) -> Self {
+        Self {
+            screen: Screen::Dashboard,
+            input: String::new(),
+            cursor_position: 0,
+            repo_root,
+            config,
+            dashboard: Vec::new(),
+            dashboard_list: FilterList::default(),
+            selected_worktree: 0,
+            message: None,
+            delete_mode: DeleteMode::default(),
+            branch_sync_status: None,
+            head_referenced: true,
+            branch_changes: None,
+            branch_merge_status: None,
+            remote_branches: Vec::new(),
+            remote_branch_list: FilterList::default(),
+            base_branch: None,
+            detach: false,
+            branch_name_check: None,
+            remotes: Vec::new(),
+            remote_list: FilterList::default(),
+            base_branch_options: Vec::new(),
+            base_option_list: FilterList::default(),
+            prune_candidates: Vec::new(),
+            prune_marked: Vec::new(),
+            candidate_list: FilterList::default(),
+            prune_results: Vec::new(),
+            log: Vec::new(),
+            hook_job: None,
+            removal_job: None,
+            fetch_job: None,
+            pending_fetches: Vec::new(),
+            created_worktree: None,
+            chosen_worktree: None,
+            launch: None,
+        }
+    }
+
+    pub fn screen(&self) -> Screen {
+        self.screen
+    }
+
+    /// Whether hooks, a removal or a fetch are running in the background,
+    /// during which keys other than Esc are ignored.
+    pub fn is_busy(&self) -> bool {
+        self.hook_job.is_some() || self.removal_job.is_some() || self.fetch_job.is_some()
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<bool> {
+        // Keys could start another operation before the hooks or removal
+        // are done
+        if self.hook_job.is_some() || self.removal_job.is_some() {
+            return Ok(false);
+        }
+        // Esc is the only way out while a fetch runs
+        if let Some(ref job) = self.fetch_job {
+            if key.code == KeyCode::Esc {
+                job.cancel();
+                self.fetch_job = None;
+                self.pending_fetches.clear();
+            }
+            return Ok(false);
+        }
+
+        match self.screen {
+            Screen::Dashboard => self.handle_dashboard_key(key),
+            Screen::CreateBranch => self.handle_create_branch_key(key),
+            Screen::SelectBaseBranchOption => self.handle_select_base_branch_option_key(key),
+            Screen::SelectRemote => self.handle_select_remote_key(key),
+            Screen::SelectRemoteBranch => self.handle_select_remote_branch_key(key),
+            Screen::ConfirmDelete => self.handle_confirm_delete_key(key),
+            Screen::Cleanup => self.handle_cleanup_key(key),
+            Screen::ConfirmCleanup => self.handle_confirm_cleanup_key(key),
+            Screen::CleanupSummary => self.handle_cleanup_summary_key(key),
+        }
+    }
+
+    fn handle_dashboard_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if self.dashboard_list.handle_key(key) {
+            return Ok(false);
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Char('c') => self.start_create(),
+            KeyCode::Char('f') => self.fetch_all()?,
+            KeyCode::Char('r') => {
+                self.message = None;
+                self.show_dashboard();
+            }
+            KeyCode::Char('x') => self.start_cleanup()?,
+            _ => {
+                let Some(selected) = self.dashboard_list.selected() else {
+                    return Ok(false);
+                };
+                let worktree = &self.dashboard[selected].worktree;
+
+                match key.code {
+                    KeyCode::Enter | KeyCode::Char('o') => {
+                        self.chosen_worktree = Some(worktree.path.clone());
+                        return Ok(true);
+                    }
+                    KeyCode::Char('d') => self.start_delete(selected)?,
+                    KeyCode::Char(c) if c == self.config.keys.shell => {
+                        let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+                        let mut command = Command::new(&shell);
+                        command.current_dir(&worktree.path);
+                        self.launch = Some((shell, command));
+                    }
+                    KeyCode::Char(c) if c == self.config.keys.editor => {
+                        let path = worktree.path.clone();
+                        self.open_editor(&path);
+                    }
+                    KeyCode::Char(c) if c == self.config.keys.tmux => {
+                        let worktree = worktree.clone();
+                        self.open_tmux_session(&worktree);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Goes back to the dashboard, reloading it since the worktrees may have
+    /// changed.
+    fn show_dashboard(&mut self) {
+        self.screen = Screen::Dashboard;
+        if let Err(e) = self.load_dashboard() {
+            self.message = Some(format!("Error: {}", e));
+        }
+    }
+
+    fn start_create(&mut self) {
+        self.input.clear();
+        self.cursor_position = 0;
+        self.message = None;
+        self.base_branch = None;
+        self.detach = false;
+        self.branch_name_check = None;
+        self.created_worktree = None;
+
+        // Build the base branch options
+        self.base_branch_options = Vec::new();
+        if let Some(ref default_branch) = self.config.default_base_branch {
+            self.base_branch_options
+                .push(format!("Use default: {}", default_branch));
+        }
+        self.base_branch_options
+            .push("Fetch from a remote...".to_string());
+        self.base_branch_options
+            .push("Use current HEAD".to_string());
+        self.base_branch_options
+            .push("Check out a tag or commit (detached)".to_string());
+        self.base_option_list = FilterList::new(self.base_branch_options.clone());
+        self.screen = Screen::SelectBaseBranchOption;
+    }
+
+    /// Reviews the removal of the worktree in row `selected` of the
+    /// dashboard. Only worktrees in `worktree_path` are gitsy's to remove.
+    fn start_delete(&mut self, selected: usize) -> Result<()> {
+        let row = &self.dashboard[selected];
+        let worktree = &row.worktree;
+        if row.main {
+            self.message = Some("Error: The main worktree can't be deleted".to_string());
+            return Ok(());
+        }
+        if !worktree.path.starts_with(self.worktree_root()) {
+            self.message = Some(format!(
+                "Error: {} is outside the worktree directory, so gitsy leaves it alone",
+                worktree.path.display()
+            ));
+            return Ok(());
+        }
+
+        self.selected_worktree = selected;
+        match worktree.branch {
+            Some(ref branch_name) => {
+                self.branch_sync_status = Some(self.branch_sync_status(branch_name)?);
+                self.branch_merge_status = self.branch_merge_status(branch_name).ok().flatten();
+                self.delete_mode = self.config.delete_mode;
+            }
+            None => {
+                self.branch_sync_status = None;
+                self.branch_merge_status = None;
+                self.head_referenced = self.head_referenced(worktree)?;
+                // There is no branch to delete along with it
+                self.delete_mode = DeleteMode::Worktree;
+            }
+        }
+        // A worktree that can't be inspected (e.g. its directory is gone)
+        // can still be removed, so this is best effort
+        self.branch_changes = self.worktree_changes(worktree).ok();
+        self.message = None;
+        self.screen = Screen::ConfirmDelete;
+        Ok(())
+    }
+
+    fn start_cleanup(&mut self) -> Result<()> {
+        self.prune_candidates = self.load_prune_candidates()?;
+        if self.prune_candidates.is_empty() {
+            self.message = Some("No merged or stale worktrees found".to_string());
+            return Ok(());
+        }
+
+        // Worktrees with uncommitted changes have to be marked by hand
+        self.prune_marked = self
+            .prune_candidates
+            .iter()
+            .map(|candidate| !candidate.blocks_removal())
+            .collect();
+        self.candidate_list = FilterList::new(
+            self.prune_candidates
+                .iter()
+                .map(|candidate| candidate.branch.clone())
+                .collect(),
+        );
+        self.screen = Screen::Cleanup;
+        self.message = None;
+        Ok(())
+    }
+
+    /// Fetches from every remote in turn, like `git fetch --all`, in the
+    /// background.
+    fn fetch_all(&mut self) -> Result<()> {
+        self.load_remotes()?;
+        let Some((first, rest)) = self.remotes.split_first() else {
+            self.message = Some("No remotes configured".to_string());
+            return Ok(());
+        };
+
+        self.pending_fetches = rest.to_vec();
+        self.fetch_job = Some(FetchJob::spawn(self.git(), first.clone()));
+        self.message = None;
+        Ok(())
+    }
+
+    fn handle_create_branch_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let input = self.input.clone();
+        let quit = self.edit_create_branch(key)?;
+        if self.input != input {
+            self.check_branch_name();
+        }
+        Ok(quit)
+    }
+
+    fn edit_create_branch(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.message = None;
+                self.show_dashboard();
+            }
+            KeyCode::Enter if self.input.is_empty() => {
+                if let Some(worktree) = self.created_worktree.take() {
+                    self.chosen_worktree = Some(worktree.path);
+                    return Ok(true);
+                }
+            }
+            KeyCode::Char('e') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                if let Some(worktree) = self.created_worktree.clone() {
+                    self.open_editor(&worktree.path);
+                }
+            }
+            KeyCode::Char('t') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                if let Some(worktree) = self.created_worktree.clone() {
+                    self.open_tmux_session(&worktree);
+                }
+            }
+            KeyCode::Enter
+                if self
+                    .branch_name_check
+                    .as_ref()
+                    .is_some_and(|check| check.blocks_creation()) =>
+            {
+                if let Some(ref check) = self.branch_name_check {
+                    self.message = Some(format!("Error: {}", check.summary(&self.input)));
+                }
+            }
+            KeyCode::Enter => {
+                let checkout = self
+                    .branch_name_check
+                    .as_ref()
+                    .and_then(|check| check.checkout());
+                let created = if self.detach {
+                    self.create_detached_worktree(&self.input)
+                } else if let Some(ref checkout) = checkout {
+                    self.checkout_worktree(&self.input, checkout)
+                } else {
+                    self.create_worktree(&self.input, self.base_branch.as_deref())
+                };
+                match created {
+                    Ok(worktree) => {
+                        self.input.clear();
+                        self.cursor_position = 0;
+                        self.log = self
+                            .share_files(&worktree.path)
+                            .iter()
+                            .map(|file| file.to_string())
+                            .collect();
+                        if self.config.tmux.enabled {
+                            self.log.push(
+                                match self
+                                    .config
+                                    .tmux
+                                    .ensure_session(&worktree.name(), &worktree.path)
+                                {
+                                    Ok(session) => format!("Created tmux session '{}'", session),
+                                    Err(e) => format!("Failed to create tmux session: {:#}", e),
+                                },
+                            );
+                        }
+                        self.created_worktree = Some(worktree.clone());
+                        let created = create_success(&worktree);
+                        self.message =
+                            Some(if self.start_hooks(HookEvent::PostCreate, worktree, None) {
+                                format!("{}, running post_create hooks...", created)
+                            } else {
+                                created
+                            });
+                    }
+                    Err(e) => {
+                        self.message = Some(format!("Error: {}", e));
+                    }
+                }
+            }
+            KeyCode::Char(c) => {
+                self.input.insert(self.cursor_position, c);
+                self.cursor_position += 1;
+            }
+            KeyCode::Backspace if self.cursor_position > 0 => {
+                self.input.remove(self.cursor_position - 1);
+                self.cursor_position -= 1;
+            }
+            KeyCode::Delete if self.cursor_position < self.input.len() => {
+                self.input.remove(self.cursor_position);
+            }
+            KeyCode::Left if self.cursor_position > 0 => {
+                self.cursor_position -= 1;
+            }
+            KeyCode::Right if self.cursor_position < self.input.len() => {
+                self.cursor_position += 1;
+            }
+            KeyCode::Home => {
+                self.cursor_position = 0;
+            }
+            KeyCode::End => {
+                self.cursor_position = self.input.len();
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Queues the editor to be run in `worktree_path`, or explains why there
+    /// is none.
+    fn open_editor(&mut self, worktree_path: &Path) {
+        let Some(editor) = self.editor() else {
+            self.message = Some(
+                "Error: No editor configured; set `editor` in .gitsy.toml, $VISUAL or $EDITOR"
+                    .to_string(),
+            );
+            return;
+        };
+
+        // Through the shell, since editors are often set with arguments like
+        // `code --wait`
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("{} .", editor))
+            .current_dir(worktree_path);
+        self.launch = Some((editor, command));
+    }
+
+    /// Creates the tmux session for the worktree if needed and queues
+    /// attaching to it.
+    fn open_tmux_session(&mut self, worktree: &Worktree) {
+        match self
+            .config
+            .tmux
+            .ensure_session(&worktree.name(), &worktree.path)
+        {
+            Ok(session) => {
+                let command = self.config.tmux.attach_command(&session);
+                self.launch = Some(("tmux".to_string(), command));
+            }
+            Err(e) => self.message = Some(format!("Error: {:#}", e)),
+        }
+    }
+
+    /// `editor` from the config, falling back to `$VISUAL` and `$EDITOR`.
+    fn editor(&self) -> Option<String> {
+        let set = |editor: &String| !editor.trim().is_empty();
+        self.config
+            .editor
+            .clone()
+            .filter(set)
+            .or_else(|| env::var("VISUAL").ok().filter(set))
+            .or_else(|| env::var("EDITOR").ok().filter(set))
+    }
+
+    fn handle_select_base_branch_option_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if self.base_option_list.handle_key(key) {
+            return Ok(false);
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.message = None;
+                self.show_dashboard();
+            }
+            KeyCode::Enter => {
+                let Some(selected) = self.base_option_list.selected() else {
+                    return Ok(false);
+                };
+                let has_default = self.config.default_base_branch.is_some();
+                let fetch_index = if has_default { 1 } else { 0 };
+                let head_index = if has_default { 2 } else { 1 };
+                let detach_index = head_index + 1;
+                self.detach = false;
+
+                if has_default && selected == 0 {
+                    // Use default branch
+                    self.base_branch = self.config.default_base_branch.clone();
+                    self.screen = Screen::CreateBranch;
+                    self.message = None;
+                } else if selected == fetch_index {
+                    // Fetch from a remote
+                    match self.load_remotes() {
+                        Ok(_) => {
+                            if self.remotes.is_empty() {
+                                self.message = Some("No remotes configured".to_string());
+                                self.screen = Screen::CreateBranch;
+                            } else {
+                                self.remote_list = FilterList::new(self.remotes.clone());
+                                self.screen = Screen::SelectRemote;
+                                self.message = None;
+                            }
+                        }
+                        Err(e) => {
+                            self.message = Some(format!("Error loading remotes: {}", e));
+                            self.screen = Screen::CreateBranch;
+                        }
+                    }
+                } else if selected == head_index {
+                    // Use current HEAD
+                    self.base_branch = None;
+                    self.screen = Screen::CreateBranch;
+                    self.message = None;
+                } else if selected == detach_index {
+                    // The input is the tag or commit to check out
+                    self.base_branch = None;
+                    self.detach = true;
+                    self.screen = Screen::CreateBranch;
+                    self.message = None;
+                }
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn handle_select_remote_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if self.remote_list.handle_key(key) {
+            return Ok(false);
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.screen = Screen::SelectBaseBranchOption;
+                self.message = None;
+            }
+            KeyCode::Enter => {
+                let Some(selected) = self.remote_list.selected() else {
+                    return Ok(false);
+                };
+                let remote = self.remotes[selected].clone();
+                self.fetch_job = Some(FetchJob::spawn(self.git(), remote));
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Once the fetch from the select remote screen is done, moves on to
+    /// picking one of the remote's branches. A fetch from the dashboard goes
+    /// on to the next remote instead.
+    fn poll_fetch(&mut self) {
+        let Some(ref mut job) = self.fetch_job else {
+            return;
+        };
+        let Some(result) = job.poll() else {
+            return;
+        };
+        let remote = job.remote.clone();
+        self.fetch_job = None;
+
+        if self.screen == Screen::Dashboard {
+            self.continue_fetch_all(&remote, result);
+            return;
+        }
+
+        match result
+            .map_err(anyhow::Error::from)
+            .and_then(|()| self.load_remote_branches_from(&remote))
+        {
+            Ok(_) => {
+                if self.remote_branches.is_empty() {
+                    self.message = Some("No remote branches found".to_string());
+                    self.screen = Screen::CreateBranch;
+                } else {
+                    self.remote_branch_list = FilterList::new(self.remote_branches.clone());
+                    self.screen = Screen::SelectRemoteBranch;
+                    self.message = None;
+                }
+            }
+            Err(e) => {
+                self.message = Some(format!("Error fetching: {}", e));
+                self.screen = Screen::CreateBranch;
+            }
+        }
+    }
+
+    /// Moves on to the next remote once the dashboard's fetch from `remote`
+    /// is done, and reloads the dashboard after the last one.
+    fn continue_fetch_all(&mut self, remote: &str, result: Result<(), GitError>) {
+        if let Err(e) = result {
+            self.pending_fetches.clear();
+            self.show_dashboard();
+            self.message = Some(format!("Error fetching from {}: {}", remote, e));
+        } else if self.pending_fetches.is_empty() {
+            self.show_dashboard();
+            self.message = Some(format!("Fetched from {}", self.remotes.join(", ")));
+        } else {
+            let next = self.pending_fetches.remove(0);
+            self.fetch_job = Some(FetchJob::spawn(self.git(), next));
+        }
+    }
+
+    fn handle_select_remote_branch_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if self.remote_branch_list.handle_key(key) {
+            return Ok(false);
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.screen = Screen::SelectRemote;
+                self.message = None;
+            }
+            KeyCode::Enter => {
+                if let Some(selected) = self.remote_branch_list.selected() {
+                    self.base_branch = Some(self.remote_branches[selected].clone());
+                    self.screen = Screen::CreateBranch;
+                    self.message = None;
+                }
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn handle_confirm_delete_key(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.screen = Screen::Dashboard;
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') if !self.delete_needs_force() => {
+                self.confirm_delete_worktree(false);
+            }
+            KeyCode::Char('f') | KeyCode::Char('F') if self.delete_needs_force() => {
+                self.confirm_delete_worktree(self.removal_blocked());
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                self.screen = Screen::Dashboard;
+            }
+            KeyCode::Tab if self.branch_sync_status.is_some() => {
+                self.delete_mode = self.delete_mode.next();
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn handle_cleanup_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if self.candidate_list.handle_key(key) {
+            return Ok(false);
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.message = None;
+                self.show_dashboard();
+            }
+            KeyCode::Char(' ') => {
+                if let Some(selected) = self.candidate_list.selected() {
+                    let marked = &mut self.prune_marked[selected];
+                    *marked = !*marked;
+                }
+            }
+            KeyCode::Char('a') => {
+                // Only the candidates the filter shows
+                let shown: Vec<usize> = self
+                    .candidate_list
+                    .matches()
+                    .iter()
+                    .map(|m| m.index)
+                    .collect();
+                let all = shown.iter().all(|&index| self.prune_marked[index]);
+                for index in shown {
+                    self.prune_marked[index] = !all;
+                }
+            }
+            KeyCode::Enter if self.prune_marked.contains(&true) => {
+                self.screen = Screen::ConfirmCleanup;
+                self.delete_mode = self.config.delete_mode;
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn handle_confirm_cleanup_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let blocked = self
+            .marked_candidates()
+            .any(|candidate| candidate.blocks_removal());
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                self.screen = Screen::Cleanup;
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') if !blocked => {
+                self.prune_marked(false);
+            }
+            KeyCode::Char('f') | KeyCode::Char('F') if blocked => {
+                self.prune_marked(true);
+            }
+            KeyCode::Tab => {
+                self.delete_mode = self.delete_mode.next();
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn handle_cleanup_summary_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if matches!(key.code, KeyCode::Enter | KeyCode::Esc) {
+            self.message = None;
+            self.show_dashboard();
+        }
+        Ok(false)
+    }
+
+    /// Removes the marked worktrees in the background, running the hooks
+    /// of one worktree at a time.
+    fn prune_marked(&mut self, force: bool) {
+        let candidates: Vec<PruneCandidate> = self.marked_candidates().cloned().collect();
+        let mode = self.delete_mode;
+        self.log.clear();
+        self.start_removal(
+            format!("Removing {} worktree(s)", candidates.len()),
+            move |app, sender| {
+                let results =
+                    app.prune_worktrees(candidates.iter(), mode, force, false, &mut |line| {
+                        let _ = sender.send(RemovalMessage::Output(line));
+                    });
+                Removed::Batch(results)
+            },
+        );
+    }
+
+    fn marked_candidates(&self) -> impl Iterator<Item = &PruneCandidate> {
+        self.prune_candidates
+            .iter()
+            .zip(&self.prune_marked)
+            .filter(|(_, marked)| **marked)
+            .map(|(candidate, _)| candidate)
+    }
+
+    /// Whether removing the selected worktree needs force, because of
+    /// uncommitted changes or a lock.
+    fn removal_blocked(&self) -> bool {
+        self.branch_changes
+            .is_some_and(|changes| changes.blocks_removal())
+            || self.dashboard[self.selected_worktree].worktree.locked
+    }
+
+    /// Why deleting the selected worktree's branch in the chosen mode could
+    /// lose commits, if it could.
+    fn branch_warning(&self) -> Option<String> {
+        let worktree = &self.dashboard[self.selected_worktree].worktree;
+        self.branch_sync_status.and_then(|status| {
+            branch_deletion_warning(
+                &worktree.name(),
+                self.delete_mode,
+                status,
+                self.branch_merge_status,
+            )
+        })
+    }
+
+    /// Whether the delete needs `f` rather than `y`: the removal needs force,
+    /// or the branch to delete is neither merged nor safely pushed.
+    fn delete_needs_force(&self) -> bool {
+        self.removal_blocked() || self.branch_warning().is_some()
+    }
+
+    fn confirm_delete_worktree(&mut self, force: bool) {
+        let worktree = self.dashboard[self.selected_worktree].worktree.clone();
+        let mode = self.delete_mode;
+        self.log.clear();
+        let name = worktree.name();
+        if self.start_hooks(HookEvent::PreDelete, worktree.clone(), Some((mode, force))) {
+            self.message = Some(format!("Running pre_delete hooks for '{}'...", name));
+        } else {
+            self.finish_delete(worktree, mode, force);
+        }
+    }
+
+    fn finish_delete(&mut self, worktree: Worktree, mode: DeleteMode, force: bool) {
+        self.start_removal(
+            format!("Deleting the worktree for '{}'", worktree.name()),
+            move |app, _| {
+                let result = app.delete_branch(&worktree, mode, force);
+                Removed::Worktree {
+                    worktree,
+                    mode,
+                    result,
+                }
+            },
+        );
+    }
+
+    /// Runs `remove` on a background thread. It gets an `App` of its own,
+    /// since removing worktrees only needs the repo root and config.
+    fn start_removal(
+        &mut self,
+        description: String,
+        remove: impl FnOnce(&App, &Sender<RemovalMessage>) -> Removed + Send + 'static,
+    ) {
+        let (sender, receiver) = mpsc::channel();
+        let app = App::new(self.repo_root.clone(), self.config.clone());
+        thread::spawn(move || {
+            let removed = remove(&app, &sender);
+            let _ = sender.send(RemovalMessage::Finished(removed));
+        });
+
+        self.removal_job = Some(RemovalJob {
+            receiver,
+            description,
+            started: Instant::now(),
+        });
+    }
+
+    /// Moves new hook output into the log, and once the removal is done,
+    /// reports how it went.
+    fn poll_removal(&mut self) {
+        let Some(ref job) = self.removal_job else {
+            return;
+        };
+
+        let removed = loop {
+            match job.receiver.try_recv() {
+                Ok(RemovalMessage::Output(line)) => self.log.push(line),
+                Ok(RemovalMessage::Finished(removed)) => break Some(removed),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => break None,
+            }
+        };
+
+        self.removal_job = None;
+        match removed {
+            Some(Removed::Worktree {
+                worktree,
+                mode,
+                result,
+            }) => {
+                self.show_dashboard();
+                match result {
+                    Ok(remote_branch) => {
+                        self.message =
+                            Some(delete_success(&worktree, mode, remote_branch.as_deref()));
+                        self.start_hooks(HookEvent::PostDelete, worktree, None);
+                    }
+                    Err(e) => {
+                        self.message = Some(format!("Error: {}", e));
+                    }
+                }
+            }
+            Some(Removed::Batch(results)) => {
+                self.prune_results = results;
+                self.screen = Screen::CleanupSummary;
+            }
+            None => {
+                self.show_dashboard();
+                self.message = Some("Error: The removal stopped unexpectedly".to_string());
+            }
+        }
+    }
+
+    fn hook_context(&self, worktree: &Worktree) -> HookContext {
+        HookContext {
+            branch: worktree.branch.clone().unwrap_or_default(),
+            worktree_path: worktree.path.clone(),
+            repo_root: self.repo_root.clone(),
+        }
+    }
+
+    /// Starts the hooks for `event` on a background thread. Returns false
+    /// when none are configured, so the caller can carry on right away.
+    fn start_hooks(
+        &mut self,
+        event: HookEvent,
+        worktree: Worktree,
+        then_delete: Option<(DeleteMode, bool)>,
+    ) -> bool {
+        let commands = self.config.hooks.commands(event).to_vec();
+        if commands.is_empty() {
+            return false;
+        }
+
+        self.hook_job = Some(HookJob {
+            event,
+            receiver: self.hook_context(&worktree).spawn(event, commands),
+            worktree,
+            then_delete,
+        });
+        true
+    }
+
+    /// Moves new hook output into the log, and once the hooks are done,
+    /// reports how they went and carries on with a pending removal.
+    fn poll_hooks(&mut self) {
+        let Some(ref job) = self.hook_job else {
+            return;
+        };
+
+        let result = loop {
+            match job.receiver.try_recv() {
+                Ok(HookMessage::Output(line)) => self.log.push(line),
+                Ok(HookMessage::Finished(result)) => break result,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    break Err(format!("{} hooks stopped unexpectedly", job.event.name()));
+                }
+            }
+        };
+
+        let Some(job) = self.hook_job.take() else {
+            return;
+        };
+        let name = job.worktree.name();
+        match (job.event, result) {
+            (HookEvent::PostCreate, Ok(())) => {
+                self.message = Some(create_success(&job.worktree));
+            }
+            (HookEvent::PreDelete, Ok(())) => {
+                let (mode, force) = job.then_delete.unwrap_or_default();
+                self.finish_delete(job.worktree, mode, force);
+            }
+            (HookEvent::PostDelete, Ok(())) => {}
+            (HookEvent::PostCreate, Err(e)) => {
+                self.message = Some(format!(
+                    "Error: {}; the worktree for branch '{}' was created and kept",
+                    e, name
+                ));
+            }
+            (HookEvent::PreDelete, Err(e)) => {
+                self.message = Some(format!(
+                    "Error: {}; the worktree for branch '{}' was not deleted",
+                    e, name
+                ));
+                self.screen = Screen::Dashboard;
+            }
+            (HookEvent::PostDelete, Err(e)) => {
+                self.message = Some(format!(
+                    "Error: {}; the worktree for branch '{}' was deleted",
+                    e, name
+                ));
+            }
+        }
+    }
+
+    /// The configured worktree directory, resolved against the repo root.
+    ///
+    /// Canonicalized when it exists so that it compares equal to the paths
+    /// reported by `git worktree list`, which never contain `..`.
+    fn worktree_root(&self) -> PathBuf {
+        let path = config::expand_worktree_path(&self.config.worktree_path, &self.repo_root);
+        let path = if path.is_absolute() {
+            path
+        } else {
+            self.repo_root.join(path)
+        };
+
+        fs::canonicalize(&path).unwrap_or(path)
+    }
+
+    /// Where a new worktree for `branch_name` goes, per
+    /// `worktree_dir_template`.
+    fn new_worktree_path(&self, branch_name: &str) -> Result<PathBuf> {
+        let dir = config::expand_template(
+            &self.config.worktree_dir_template,
+            &self.repo_root,
+            Some(branch_name),
+        );
+
+        // Worktrees outside the root wouldn't be listed as gitsy's own
+        let relative = Path::new(&dir);
+        if dir.is_empty()
+            || relative.is_absolute()
+            || relative
+                .components()
+                .any(|component| component == std::path::Component::ParentDir)
+        {
+            return Err(anyhow::anyhow!(
+                "worktree_dir_template must give a relative path inside worktree_path, not '{}'",
+                dir
+            ));
+        }
+
+        Ok(self.worktree_root().join(relative))
+    }
+
+    fn create_worktree(&self, branch_name: &str, base: Option<&str>) -> Result<Worktree> {
+        let branch_path = self.new_worktree_path(branch_name)?;
+        // If we have a base branch (from remote), use it as the starting point
+        self.add_worktree(
+            &branch_path,
+            NewWorktree::Branch {
+                name: branch_name,
+                base,
+            },
+        )
+    }
+
+    /// Checks out a branch that already exists, locally or on a remote, in a
+    /// new worktree rather than creating it.
+    fn checkout_worktree(&self, branch_name: &str, checkout: &Checkout) -> Result<Worktree> {
+        let branch_path = self.new_worktree_path(branch_name)?;
+        match checkout {
+            Checkout::Local => self.add_worktree(&branch_path, NewWorktree::Existing(branch_name)),
+            Checkout::Remote(remote_branch) => self.add_worktree(
+                &branch_path,
+                NewWorktree::Tracking {
+                    name: branch_name,
+                    remote_branch,
+                },
+            ),
+        }
+    }
+
+    /// Checks out `target`, a tag or commit, in a new worktree with a
+    /// detached HEAD. The directory is named after `target` as if it were a
+    /// branch.
+    fn create_detached_worktree(&self, target: &str) -> Result<Worktree> {
+        let path = self.new_worktree_path(target)?;
+        self.add_worktree(&path, NewWorktree::Detached(target))
+    }
+
+    fn add_worktree(&self, path: &Path, new: NewWorktree) -> Result<Worktree> {
+        self.git().add_worktree(path, new)?;
+        self.find_worktree(path)
+    }
+
+    /// The configured backend for operations on this repository.
+    fn git(&self) -> Box<dyn GitBackend> {
+        self.config.git_backend.open(&self.repo_root)
+    }
+
+    fn branch_name_check(&self, branch_name: &str) -> Result<BranchNameCheck> {
+        let repo = Repository::open(&self.repo_root)?;
+        let dir = self.new_worktree_path(branch_name).ok();
+        BranchNameCheck::of(&repo, branch_name, dir, &self.list_worktrees()?)
+    }
+
+    /// Rechecks the create screen's input after it changed.
+    fn check_branch_name(&mut self) {
+        self.branch_name_check = if self.detach || self.input.is_empty() {
+            None
+        } else {
+            self.branch_name_check(&self.input).ok()
+        };
+    }
+
+    /// Copies and symlinks the configured untracked files from the repository
+    /// root into a freshly created worktree.
+    fn share_files(&self, worktree_path: &Path) -> Vec<SharedFile> {
+        shared_files::materialize(
+            &self.repo_root,
+            worktree_path,
+            &self.worktree_root(),
+            &self.config.copy,
+            &self.config.symlink,
+            self.config.file_conflict,
+        )
+    }
+
+    fn load_remotes(&mut self) -> Result<()> {
+        self.remotes = self.git().remotes()?;
+        Ok(())
+    }
+
+    fn fetch_and_load_remote_branches_from(&mut self, remote: &str) -> Result<()> {
+        self.git()
+            .fetch(remote, &AtomicBool::new(false), &mut |_| {})?;
+        self.load_remote_branches_from(remote)
+    }
+
+    /// Lists the remote-tracking branches of `remote` as of the last fetch.
+    fn load_remote_branches_from(&mut self, remote: &str) -> Result<()> {
+        self.remote_branches = self.git().remote_branches(remote)?;
+        Ok(())
+    }
+
+    /// Lists the worktrees that live inside the gitsy workspace.
+    fn load_worktrees(&self) -> Result<Vec<Worktree>> {
+        let worktree_path = self.worktree_root();
+
+        let worktrees = self
+            .list_worktrees()?
+            .into_iter()
+            .filter(|worktree| worktree.path.starts_with(&worktree_path))
+            .collect();
+
+        Ok(worktrees)
+    }
+
+    /// Lists every worktree git knows about, in `git worktree list` order.
+    fn list_worktrees(&self) -> Result<Vec<Worktree>> {
+        let mut worktrees = self.git().list_worktrees()?;
+        let repo = Repository::open(&self.repo_root)?;
+        worktree::describe_detached(&repo, &mut worktrees);
+        Ok(worktrees)
+    }
+
+    /// Loads every worktree of the repository for the dashboard, the main
+    /// one first, along with its sync and merge status, changes and last
+    /// commit.
+    fn load_dashboard(&mut self) -> Result<()> {
+        let repo = Repository::open(&self.repo_root)?;
+        let repo_root = fs::canonicalize(&self.repo_root).unwrap_or(self.repo_root.clone());
+
+        self.dashboard = self
+            .list_worktrees()?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, worktree)| !worktree.bare)
+            .map(|(i, worktree)| DashboardRow {
+                // `git worktree list` always puts the main worktree first
+                main: i == 0,
+                path: dashboard::relative_path(&worktree.path, &repo_root),
+                sync: worktree
+                    .branch
+                    .as_deref()
+                    .and_then(|branch| self.branch_sync_status(branch).ok()),
+                // The base branch itself has nothing to merge
+                merge: worktree
+                    .branch
+                    .as_deref()
+                    .filter(|branch| self.config.default_base_branch.as_deref() != Some(branch))
+                    .and_then(|branch| self.branch_merge_status(branch).ok().flatten()),
+                changes: self.worktree_changes(&worktree).ok(),
+                last_commit: worktree
+                    .head
+                    .as_deref()
+                    .and_then(|head| LastCommit::of_head(&repo, head)),
+                worktree,
+            })
+            .collect();
+        self.dashboard_list = FilterList::new(
+            self.dashboard
+                .iter()
+                .map(|row| row.worktree.label())
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn branch_sync_status(&self, branch_name: &str) -> Result<SyncStatus> {
+        let repo = Repository::open(&self.repo_root)?;
+        SyncStatus::of_branch(&repo, branch_name)
+    }
+
+    /// Checks the branch against `default_base_branch`, or returns `None`
+    /// when no base is configured.
+    fn branch_merge_status(&self, branch_name: &str) -> Result<Option<MergeStatus>> {
+        let Some(ref base) = self.config.default_base_branch else {
+            return Ok(None);
+        };
+
+        let repo = Repository::open(&self.repo_root)?;
+        MergeStatus::of_branch(&repo, branch_name, base).map(Some)
+    }
+
+    /// Gitsy worktrees whose branch has been merged into `default_base_branch`
+    /// or whose upstream has been deleted.
+    fn load_prune_candidates(&self) -> Result<Vec<PruneCandidate>> {
+        let mut candidates = Vec::new();
+
+        for worktree in self.load_worktrees()? {
+            let Some(branch) = worktree.branch.clone() else {
+                continue;
+            };
+            let sync = self.branch_sync_status(&branch).ok();
+            let merge = self.branch_merge_status(&branch).ok().flatten();
+
+            if let Some(reason) = PruneReason::of(sync, merge) {
+                let changes = self.worktree_changes(&worktree).ok();
+                candidates.push(PruneCandidate {
+                    branch,
+                    worktree,
+                    reason,
+                    changes,
+                    sync,
+                    merge,
+                });
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    /// Removes each candidate's worktree, and its branch as `mode` says,
+    /// carrying on past failures so one bad worktree doesn't hold up the
+    /// rest of the batch. Branches that are not known to be merged are
+    /// kept unless `delete_unmerged` is set.
+    fn prune_worktrees<'a>(
+        &self,
+        candidates: impl Iterator<Item = &'a PruneCandidate>,
+        mode: DeleteMode,
+        force: bool,
+        delete_unmerged: bool,
+        on_output: &mut dyn FnMut(String),
+    ) -> Vec<PruneResult> {
+        candidates
+            .map(|candidate| {
+                let kept_branch = !delete_unmerged && keeps_branch(candidate, mode);
+                let mode = if kept_branch {
+                    DeleteMode::Worktree
+                } else {
+                    mode
+                };
+                match self.delete_with_hooks(&candidate.worktree, mode, force, on_output) {
+                    Ok(outcome) => PruneResult {
+                        branch: candidate.branch.clone(),
+                        error: None,
+                        hook_error: outcome.hook_error,
+                        kept_branch,
+                    },
+                    Err(e) => PruneResult {
+                        branch: candidate.branch.clone(),
+                        error: Some(e.to_string()),
+                        hook_error: None,
+                        kept_branch,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// The gitsy worktree that has `name` checked out, or for a detached
+    /// worktree, whose directory is called `name`.
+    fn named_worktree(&self, name: &str) -> Result<Worktree> {
+        self.load_worktrees()?
+            .into_iter()
+            .find(|worktree| worktree.name() == name)
+            .with_context(|| format!("No gitsy worktree found for branch '{}'", name))
+    }
+
+    /// Whether removing a detached worktree would leave its HEAD unreachable.
+    fn head_referenced(&self, worktree: &Worktree) -> Result<bool> {
+        let Some(ref head) = worktree.head else {
+            return Ok(true);
+        };
+        let repo = Repository::open(&self.repo_root)?;
+        worktree::head_is_referenced(&repo, head)
+    }
+
+    /// Looks up the entry git recorded for a worktree that was just added.
+    fn find_worktree(&self, path: &Path) -> Result<Worktree> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.list_worktrees()?
+            .into_iter()
+            .find(|worktree| worktree.path == path)
+            .with_context(|| format!("git does not list a worktree at {}", path.display()))
+    }
+
+    fn worktree_changes(&self, worktree: &Worktree) -> Result<WorktreeChanges> {
+        WorktreeChanges::of_worktree(&worktree.path, worktree.branch.as_deref())
+    }
+
+    /// Removes the worktree. With `force`, uncommitted and untracked files in
+    /// it are discarded and a lock is ignored instead of aborting the
+    /// removal. A worktree whose directory is already gone is pruned.
+    fn delete_worktree(&self, worktree: &Worktree, force: bool) -> Result<()> {
+        self.git().remove_worktree(worktree, force)?;
+
+        if self.config.tmux.enabled {
+            // The worktree is gone either way, and a leftover session is
+            // easy to kill by hand
+            let _ = self.config.tmux.kill_session(&worktree.name());
+        }
+
+        Ok(())
+    }
+
+    /// `delete_branch` between the `pre_delete` and `post_delete` hooks, for
+    /// callers that can wait for them. A failing `pre_delete` hook keeps the
+    /// worktree.
+    fn delete_with_hooks(
+        &self,
+        worktree: &Worktree,
+        mode: DeleteMode,
+        force: bool,
+        on_output: &mut dyn FnMut(String),
+    ) -> Result<DeleteOutcome> {
+        let context = self.hook_context(worktree);
+
+        context
+            .run(
+                HookEvent::PreDelete,
+                self.config.hooks.commands(HookEvent::PreDelete),
+                on_output,
+            )
+            .map_err(|e| anyhow::anyhow!("{}; the worktree was not deleted", e))?;
+
+        let remote_branch = self.delete_branch(worktree, mode, force)?;
+
+        let hook_error = context
+            .run(
+                HookEvent::PostDelete,
+                self.config.hooks.commands(HookEvent::PostDelete),
+                on_output,
+            )
+            .err()
+            .map(|e| e.to_string());
+
+        Ok(DeleteOutcome {
+            remote_branch,
+            hook_error,
+        })
+    }
+
+    /// The remote and branch name that `branch_name` tracks, as long as the
+    /// remote branch still exists.
+    fn branch_upstream(&self, branch_name: &str) -> Result<Option<(String, String)>> {
+        let repo = Repository::open(&self.repo_root)?;
+        if repo
+            .find_branch(branch_name, BranchType::Local)?
+            .upstream()
+            .is_err()
+        {
+            return Ok(None);
+        }
+
+        let remote = repo.branch_upstream_remote(&format!("refs/heads/{}", branch_name))?;
+        let remote = remote
+            .as_str()
+            .context("Upstream remote name is not valid UTF-8")?;
+        let merge = repo
+            .config()?
+            .get_string(&format!("branch.{}.merge", branch_name))?;
+
+        // A branch tracking another local branch has nothing to push
+        if remote == "." {
+            return Ok(None);
+        }
+
+        let remote_branch = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+        Ok(Some((remote.to_string(), remote_branch.to_string())))
+    }
+
+    /// Removes the worktree and, depending on `mode`, its local branch and
+    /// the branch's remote branch. Returns the remote branch that was
+    /// deleted, if any; there is none when the branch was never pushed or its
+    /// upstream is already gone. A detached worktree has no branch to delete.
+    fn delete_branch(
+        &self,
+        worktree: &Worktree,
+        mode: DeleteMode,
+        force: bool,
+    ) -> Result<Option<String>> {
+        let Some(ref branch_name) = worktree.branch else {
+            self.delete_worktree(worktree, force)?;
+            return Ok(None);
+        };
+
+        // The upstream is recorded in the branch's config, which goes away
+        // with the local branch
+        let upstream = match mode {
+            DeleteMode::RemoteBranch => self.branch_upstream(branch_name)?,
+            _ => None,
+        };
+
+        self.delete_worktree(worktree, force)?;
+
+        if mode.deletes_branch() {
+            // Whether or not it is merged, since squash-merged branches don't
+            // look merged to git, and the caller has already warned about
+            // unmerged work
+            self.git().delete_branch(branch_name).map_err(|e| {
+                anyhow::anyhow!(
+                    "Removed the worktree, but deleting the branch failed: {}",
+                    e
+                )
+            })?;
+        }
+
+        let Some((remote, remote_branch)) = upstream else {
+            return Ok(None);
+        };
+
+        self.git()
+            .delete_remote_branch(&remote, &remote_branch)
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Removed the worktree and local branch, but deleting the remote branch failed: {}",
+                    e
+                )
+            })?;
+
+        Ok(Some(format!("{}/{}", remote, remote_branch)))
+    }
+}
+
+const DELETE_PROMPT: &str = "Are you sure you want to delete this worktree? (y/N)";
+const FORCE_DELETE_PROMPT: &str = "Force removal of this worktree and discard its changes? (f/N)";
+const UNMERGED_DELETE_PROMPT: &str = "Delete this worktree and its unmerged branch anyway? (f/N)";
+const CLEANUP_PROMPT: &str = "Remove these worktrees? (y/N)";
+const FORCE_CLEANUP_PROMPT: &str =
+    "Force removal of these worktrees and discard their changes? (f/N)";
+
+const DASHBOARD_COLUMNS: [&str; 7] = [
+    "Branch",
+    "Path",
+    "Ahead/Behind",
+    "Merged",
+    "Changes",
+    "Last commit",
+    "Age",
+];
+
+/// The cells of a dashboard row. `branch` is the label as the filter list
+/// styled it, and `style` marks the selection.
+fn dashboard_cells(
+    row: &DashboardRow,
+    style: Style,
+    mut branch: Vec<Span<'static>>,
+) -> [Line<'static>; 7] {
+    let dim = Style::default().fg(Color::DarkGray);
+
+    branch.push(worktree_state_span(&row.worktree));
+    let mut path = vec![Span::styled(row.path.display().to_string(), style)];
+    if row.main {
+        path.push(Span::styled(" (main)", dim));
+    }
+    let sync = match row.sync {
+        Some(status) if status.is_at_risk() => Span::styled(
+            dashboard::ahead_behind(status),
+            Style::default().fg(Color::Red),
+        ),
+        Some(status) => Span::styled(
+            dashboard::ahead_behind(status),
+            Style::default().fg(Color::Green),
+        ),
+        None => Span::styled("-", dim),
+    };
+    let merge = match row.merge {
+        Some(merge) if merge.is_merged() => {
+            Span::styled(merge.to_string(), Style::default().fg(Color::Green))
+        }
+        Some(merge) => Span::styled(merge.to_string(), dim),
+        None => Span::styled("-", dim),
+    };
+    let changes = match row.changes {
+        Some(changes) if changes.blocks_removal() => {
+            Span::styled(changes.to_string(), Style::default().fg(Color::Yellow))
+        }
+        Some(changes) => Span::styled(changes.to_string(), dim),
+        None => Span::styled("?", dim),
+    };
+    let (subject, age) = match row.last_commit {
+        Some(ref commit) => (commit.subject.clone(), commit.age()),
+        None => (String::new(), String::new()),
+    };
+
+    [
+        Line::from(branch),
+        Line::from(path),
+        Line::from(sync),
+        Line::from(merge),
+        Line::from(changes),
+        Line::styled(subject, style),
+        Line::styled(age, dim),
+    ]
+}
+
+/// Every dashboard column fits its widest cell, except the commit subject,
+/// which takes the rest.
+fn dashboard_widths(rows: &[DashboardRow]) -> Vec<Constraint> {
+    let cells: Vec<[Line; 7]> = rows
+        .iter()
+        .map(|row| dashboard_cells(row, Style::default(), vec![Span::raw(row.worktree.label())]))
+        .collect();
+
+    DASHBOARD_COLUMNS
+        .iter()
+        .enumerate()
+        .map(|(column, header)| {
+            if *header == "Last commit" {
+                return Constraint::Fill(1);
+            }
+            let widest = cells
+                .iter()
+                .map(|row| row[column].width())
+                .chain([header.len()])
+                .max()
+                .unwrap_or(0);
+            Constraint::Length(widest as u16)
+        })
+        .collect()
+}
+
+/// `[locked]` or `[prunable]` when git flags the worktree, since either
+/// changes what deleting it does.
+fn worktree_state_span(worktree: &Worktree) -> Span<'static> {
+    if worktree.prunable {
+        Span::styled(" [prunable]", Style::default().fg(Color::DarkGray))
+    } else if worktree.locked {
+        Span::styled(" [locked]", Style::default().fg(Color::Red))
+    } else {
+        Span::raw("")
+    }
+}
+
+/// What deleting a detached worktree could lose, the counterpart of
+/// `delete_warning` for worktrees without a branch.
+fn detached_warning(worktree: &Worktree, head_referenced: bool) -> String {
+    if head_referenced {
+        format!(
+            "Worktree '{}' is detached at {}, which is on a branch or tag; no commits will be lost.",
+            worktree.name(),
+            worktree.label()
+        )
+    } else {
+        format!(
+            "WARNING: Worktree '{}' is detached at {}, which is not on any branch or tag; its commits will be lost!",
+            worktree.name(),
+            worktree.label()
+        )
+    }
+}
+
+/// The sync summary shown before deleting a worktree, shared by the
+/// `ConfirmDelete` screen and `gitsy delete`.
+fn delete_warning(branch_name: &str, status: SyncStatus) -> String {
+    match status {
+        SyncStatus::Synced => format!("Branch '{}' is in sync with its upstream.", branch_name),
+        SyncStatus::Behind(behind) => format!(
+            "Branch '{}' is {} commit(s) behind its upstream; no local work will be lost.",
+            branch_name, behind
+        ),
+        SyncStatus::Ahead(ahead) => format!(
+            "WARNING: Branch '{}' has {} unpushed commit(s)!",
+            branch_name, ahead
+        ),
+        SyncStatus::Diverged(ahead, behind) => format!(
+            "WARNING: Branch '{}' has diverged from its upstream ({} ahead, {} behind)!",
+            branch_name, ahead, behind
+        ),
+        SyncStatus::NoUpstream => format!(
+            "WARNING: Branch '{}' has no upstream, its commits may only exist locally!",
+            branch_name
+        ),
+        SyncStatus::UpstreamGone => format!(
+            "WARNING: The upstream of branch '{}' no longer exists!",
+            branch_name
+        ),
+    }
+}
+
+/// Warns when deleting the branch refs could lose commits: the local branch
+/// is only safe to drop once its work is merged or pushed, the remote branch
+/// only once it is merged.
+fn branch_deletion_warning(
+    branch_name: &str,
+    mode: DeleteMode,
+    sync: SyncStatus,
+    merge: Option<MergeStatus>,
+) -> Option<String> {
+    // A branch without work of its own has nothing to lose either
+    let merged = merge.is_some_and(|merge| merge.is_merged() || merge == MergeStatus::NoChanges);
+    match mode {
+        DeleteMode::Worktree => None,
+        DeleteMode::LocalBranch if merged || !sync.is_at_risk() => None,
+        DeleteMode::RemoteBranch if merged => None,
+        DeleteMode::LocalBranch => Some(format!(
+            "WARNING: Branch '{}' is not merged or pushed, deleting it may lose its commits!",
+            branch_name
+        )),
+        DeleteMode::RemoteBranch => Some(format!(
+            "WARNING: Branch '{}' is not known to be merged, deleting it and its remote branch may lose its commits!",
+            branch_name
+        )),
+    }
+}
+
+/// Whether a batch removal in `mode` should leave the candidate's branch
+/// alone, as deleting it could lose commits. Only a branch whose upstream
+/// is gone can be a candidate without being merged.
+fn keeps_branch(candidate: &PruneCandidate, mode: DeleteMode) -> bool {
+    candidate.sync.is_some_and(|sync| {
+        branch_deletion_warning(&candidate.branch, mode, sync, candidate.merge).is_some()
+    })
+}
+
+fn create_success(worktree: &Worktree) -> String {
+    match worktree.branch {
+        Some(ref branch_name) => {
+            format!("Successfully created worktree for branch '{}'", branch_name)
+        }
+        None => format!(
+            "Successfully created worktree '{}' detached at {}",
+            worktree.name(),
+            worktree.label()
+        ),
+    }
+}
+
+fn delete_success(worktree: &Worktree, mode: DeleteMode, remote_branch: Option<&str>) -> String {
+    let Some(ref branch_name) = worktree.branch else {
+        return format!(
+            "Successfully deleted detached worktree '{}'",
+            worktree.name()
+        );
+    };
+    match (mode, remote_branch) {
+        (DeleteMode::Worktree, _) => {
+            format!("Successfully deleted worktree for branch '{}'", branch_name)
+        }
+        (DeleteMode::LocalBranch, _) => format!(
+            "Successfully deleted worktree and local branch '{}'",
+            branch_name
+        ),
+        (DeleteMode::RemoteBranch, Some(remote_branch)) => format!(
+            "Successfully deleted worktree, local branch '{}' and remote branch '{}'",
+            branch_name, remote_branch
+        ),
+        (DeleteMode::RemoteBranch, None) => format!(
+            "Successfully deleted worktree and local branch '{}'; it had no remote branch to delete",
+            branch_name
+        ),
+    }
+}
+
+/// The choice of what to delete, with the selected mode marked.
+fn delete_mode_line(selected: DeleteMode) -> String {
+    let options: Vec<String> = DeleteMode::ALL
+        .iter()
+        .map(|mode| {
+            let marker = if *mode == selected { "(•)" } else { "( )" };
+            format!("{} {}", marker, mode)
+        })
+        .collect();
+    format!("Remove: {}", options.join("  "))
+}
+
+fn merge_summary(branch_name: &str, merge: MergeStatus, base: &str) -> String {
+    match merge {
+        MergeStatus::Merged => format!("Branch '{}' has been merged into {}.", branch_name, base),
+        MergeStatus::SquashMerged => format!(
+            "Branch '{}' has been squash-merged into {} (its changes are already there).",
+            branch_name, base
+        ),
+        MergeStatus::NotMerged => {
+            format!(
+                "Branch '{}' has NOT been merged into {}.",
+                branch_name, base
+            )
+        }
+        MergeStatus::NoChanges => format!(
+            "Branch '{}' has no changes of its own compared to {}.",
+            branch_name, base
+        ),
+    }
+}
+
+/// Describes what removing the worktree would throw away, if anything.
+fn changes_warning(changes: &WorktreeChanges) -> Option<String> {
+    let mut lines = Vec::new();
+    if changes.blocks_removal() {
+        lines.push(format!(
+            "WARNING: The worktree has uncommitted changes ({}) that forcing the removal will discard!",
+            changes
+        ));
+    }
+    if changes.stashes > 0 {
+        lines.push(format!(
+            "Note: {} stash(es) made on this branch will be kept.",
+            changes.stashes
+        ));
+    }
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+fn find_git_root() -> Result<PathBuf> {
+    let current_dir = std::env::current_dir().context("Failed to get current directory")?;
+    let repo = Repository::discover(&current_dir)
+        .context("Not a git repository (or any parent up to mount point)")?;
+
+    let workdir = repo
+        .workdir()
+        .context("Repository doesn't have a working directory")?;
+
+    Ok(workdir.to_path_buf())
+}
+
+/// Merges the global config, .gitsy.toml and .gitsy.local.toml.
+fn load_config(repo_root: &Path) -> Result<GitsyConfig> {
+    let merged = config::load_merged(repo_root)?;
+
+    if !merged.contains_key("worktree_path") {
+        return Err(anyhow::anyhow!(
+            "No worktree_path configured in .gitsy.toml, .gitsy.local.toml or {}; run gitsy without arguments to set it up",
+            config::global_config_path()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| "the global config".to_string())
+        ));
+    }
+
+    let config: GitsyConfig = toml::Value::Table(merged)
+        .try_into()
+        .context("Failed to parse config")?;
+    config.keys.check()?;
+    Ok(config)
+}
+
+/// Runs the setup wizard when no layer sets `worktree_path`, and saves its
+/// answers to .gitsy.toml.
+fn load_or_create_config(repo_root: &Path) -> Result<GitsyConfig> {
+    if !config::load_merged(repo_root)?.contains_key("worktree_path") {
+        let answers = run_tui_setup(repo_root)?;
+        config::update(repo_root, Layer::Repo, |table| {
+            table.insert("worktree_path", toml_edit::value(answers.worktree_path));
+            if let Some(base) = answers.default_base_branch {
+                table.insert("default_base_branch", toml_edit::value(base));
+            }
+        })?;
+    }
+
+    load_config(repo_root)
+}
+
+fn run_tui_setup(repo_root: &Path) -> Result<GitsyConfig> {
+    #[derive(Debug, PartialEq)]
+    enum SetupStep {
+        WorktreePath,
+        DefaultBaseBranch,
+    }
+
+    #[derive(Debug)]
+    struct SetupApp {
+        step: SetupStep,
+        input: String,
+        cursor_position: usize,
+        repo_root: PathBuf,
+        worktree_path: String,
+    }
+
+    impl SetupApp {
+        fn new(repo_root: PathBuf) -> Self {
+            Self {
+                step: SetupStep::WorktreePath,
+                input: String::new(),
+                cursor_position: 0,
+                repo_root,
+                worktree_path: String::new(),
+            }
+        }
+
+        fn handle_key_event(&mut self, key: KeyEvent) -> bool {
+            match key.code {
+                KeyCode::Enter => match self.step {
+                    SetupStep::WorktreePath => {
+                        if !self.input.is_empty() {
+                            self.worktree_path = self.input.clone();
+                            self.input.clear();
+                            self.cursor_position = 0;
+                            self.step = SetupStep::DefaultBaseBranch;
+                        }
+                    }
+                    SetupStep::DefaultBaseBranch => {
+                        return true;
+                    }
+                },
+                KeyCode::Char(c) => {
+                    self.input.insert(self.cursor_position, c);
+                    self.cursor_position += 1;
+                }
+                KeyCode::Backspace if self.cursor_position > 0 => {
+                    self.input.remove(self.cursor_position - 1);
+                    self.cursor_position -= 1;
+                }
+                KeyCode::Delete if self.cursor_position < self.input.len() => {
+                    self.input.remove(self.cursor_position);
+                }
+                KeyCode::Left if self.cursor_position > 0 => {
+                    self.cursor_position -= 1;
+                }
+                KeyCode::Right if self.cursor_position < self.input.len() => {
+                    self.cursor_position += 1;
+                }
+                KeyCode::Home => {
+                    self.cursor_position = 0;
+                }
+                KeyCode::End => {
+                    self.cursor_position = self.input.len();
+                }
+                _ => {}
+            }
+            false
+        }
+    }
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut app = SetupApp::new(repo_root.to_path_buf());
+
+    let result: Result<()> = (|| loop {
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(2)
+                .constraints(
+                    [
+                        Constraint::Length(3),
+                        Constraint::Length(3),
+                        Constraint::Length(3),
+                        Constraint::Min(0),
+                    ]
+                    .as_ref(),
+                )
+                .split(f.area());
+
+            let title = Paragraph::new("Gitsy - Git Worktree Manager")
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+                .block(Block::default().borders(Borders::ALL));
+            f.render_widget(title, chunks[0]);
+
+            match app.step {
+                SetupStep::WorktreePath => {
+                    let instructions = Paragraph::new(
+                        "Enter the path where gitsy worktrees will be stored (Press Enter to confirm, Ctrl+C to cancel):",
+                    )
+                    .style(Style::default().fg(Color::White))
+                    .block(Block::default().borders(Borders::NONE));
+                    f.render_widget(instructions, chunks[1]);
+
+                    let input = Paragraph::new(app.input.as_str())
+                        .style(Style::default().fg(Color::Yellow))
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .title("Worktree Path"),
+                        );
+                    f.render_widget(input, chunks[2]);
+
+                    let info = vec![
+                        Line::from(vec![
+                            Span::styled("Git Repository: ", Style::default().fg(Color::Green)),
+                            Span::raw(app.repo_root.display().to_string()),
+                        ]),
+                        Line::from(""),
+                        Line::from(Span::styled(
+                            "The path can be absolute or relative to the repository root.",
+                            Style::default().fg(Color::DarkGray),
+                        )),
+                    ];
+                    let info_widget =
+                        Paragraph::new(info).block(Block::default().borders(Borders::NONE));
+                    f.render_widget(info_widget, chunks[3]);
+                }
+                SetupStep::DefaultBaseBranch => {
+                    let instructions = Paragraph::new(
+                        "Enter the default base branch for new worktrees (e.g., origin/main). Leave empty to skip:",
+                    )
+                    .style(Style::default().fg(Color::White))
+                    .block(Block::default().borders(Borders::NONE));
+                    f.render_widget(instructions, chunks[1]);
+
+                    let input = Paragraph::new(app.input.as_str())
+                        .style(Style::default().fg(Color::Yellow))
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .title("Default Base Branch (optional)"),
+                        );
+                    f.render_widget(input, chunks[2]);
+
+                    let info = vec![
+                        Line::from(vec![
+                            Span::styled("Worktree Path: ", Style::default().fg(Color::Green)),
+                            Span::raw(&app.worktree_path),
+                        ]),
+                        Line::from(""),
+                        Line::from(Span::styled(
+                            "This branch will be offered as the first option when creating new worktrees.",
+                            Style::default().fg(Color::DarkGray),
+                        )),
+                    ];
+                    let info_widget =
+                        Paragraph::new(info).block(Block::default().borders(Borders::NONE));
+                    f.render_widget(info_widget, chunks[3]);
+                }
+            }
+
+            f.set_cursor_position((
+                chunks[2].x + app.cursor_position as u16 + 1,
+                chunks[2].y + 1,
+            ));
+        })?;
+
+        if event::poll(std::time::Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+        {
+            if key.code == KeyCode::Esc
+                || (key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(event::KeyModifiers::CONTROL))
+            {
+                return Err(anyhow::anyhow!("Setup cancelled by user"));
+            }
+
+            if app.handle_key_event(key) {
+                return Ok(());
+            }
+        }
+    })();
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    result?;
+
+    let default_base_branch = if app.input.is_empty() {
+        None
+    } else {
+        Some(app.input)
+    };
+
+    Ok(GitsyConfig {
+        worktree_path: app.worktree_path,
+        default_base_branch,
+        ..Default::default()
+    })
+}
+
+/// Runs the TUI, returning the worktree the user chose to switch to.
+fn run_main_app(repo_root: PathBuf, config: GitsyConfig) -> Result<Option<PathBuf>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut app = App::new(repo_root, config);
+    let result = run_app(&mut terminal, &mut app, &mut TerminalEvents);
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    result.map(|()| app.chosen_worktree)
+}
+
+/// Hands the terminal to `f`, e.g. to run a shell or editor in the
+/// foreground, then takes it back and redraws.
+fn suspended<B: ratatui::backend::Backend, T>(
+    terminal: &mut Terminal<B>,
+    f: impl FnOnce() -> io::Result<T>,
+) -> io::Result<T> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    terminal.show_cursor()?;
+
+    let result = f();
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+
+    result
+}
+
+/// Where the TUI reads its input from: the terminal, or a script in tests.
+pub trait EventSource {
+    /// The next event, or `None` when none arrives within `timeout`.
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+
+    /// Called before every `next` with the screen shown and whether the app
+    /// is busy, for sources that wait on either.
+    fn observe(&mut self, _screen: Screen, _busy: bool) {}
+}
+
+/// Events typed into the terminal.
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            event::read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Draws `app` and hands it events until it quits or Ctrl+C is pressed.
+/// The timeout on `events` keeps the screen redrawing while hooks or a
+/// fetch run in the background.
+pub fn run_app<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    events: &mut impl EventSource,
+) -> Result<()> {
+    app.show_dashboard();
+
+    loop {
+        app.poll_hooks();
+        app.poll_removal();
+        app.poll_fetch();
+
+        terminal.draw(|f| {
+            let (main_area, log_area) = if app.log.is_empty() && app.hook_job.is_none() {
+                (f.area(), None)
+            } else {
+                let areas = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(0), Constraint::Length(10)].as_ref())
+                    .split(f.area());
+                (areas[0], Some(areas[1]))
+            };
+
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(2)
+                .constraints(
+                    [
+                        Constraint::Length(3),
+                        Constraint::Min(10),
+                        Constraint::Length(3),
+                    ]
+                    .as_ref(),
+                )
+                .split(main_area);
+
+            let title = Paragraph::new("Gitsy - Git Worktree Manager")
+                .style(
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .block(Block::default().borders(Borders::ALL));
+            f.render_widget(title, chunks[0]);
+
+            match app.screen {
+                Screen::Dashboard => {
+                    let header = Row::new(DASHBOARD_COLUMNS).style(
+                        Style::default()
+                            .fg(Color::Cyan)
+                            .add_modifier(Modifier::BOLD),
+                    );
+                    let title = format!("Worktrees ({})", app.dashboard.len());
+                    app.dashboard_list.render_table(
+                        f,
+                        chunks[1],
+                        &title,
+                        header,
+                        &dashboard_widths(&app.dashboard),
+                        |i, style, branch| Row::new(dashboard_cells(&app.dashboard[i], style, branch)),
+                    );
+
+                    let dim = Style::default().fg(Color::DarkGray);
+                    let keys = &app.config.keys;
+                    let actions = match app.fetch_job {
+                        Some(ref job) => Line::styled(
+                            format!("{} (Esc to cancel)", job.status()),
+                            Style::default().fg(Color::Yellow),
+                        ),
+                        None => Line::styled(
+                            "c to create, d to delete, f to fetch, r to refresh, x to clean up merged worktrees, q to quit",
+                            dim,
+                        ),
+                    };
+                    let instructions = Paragraph::new(vec![
+                        actions,
+                        Line::styled(
+                            format!(
+                                "Use ↑/↓ or j/k to navigate, / to filter, Enter or o to switch to it, {} for a shell, {} for the editor, {} for tmux",
+                                keys.shell, keys.editor, keys.tmux
+                            ),
+                            dim,
+                        ),
+                    ]);
+                    f.render_widget(instructions, chunks[2]);
+                }
+                Screen::CreateBranch => {
+                    let content_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints(
+                            [
+                                Constraint::Length(3),
+                                Constraint::Length(1),
+                                Constraint::Min(0),
+                            ]
+                            .as_ref(),
+                        )
+                        .split(chunks[1]);
+
+                    let input = Paragraph::new(app.input.as_str())
+                        .style(Style::default().fg(Color::Yellow))
+                        .block(Block::default().borders(Borders::ALL).title(if app.detach {
+                            "Enter a tag or commit to check out"
+                        } else {
+                            "Enter new branch name"
+                        }));
+                    f.render_widget(input, content_chunks[0]);
+
+                    if let Some(ref check) = app.branch_name_check {
+                        let style = if check.blocks_creation() {
+                            Style::default().fg(Color::Red)
+                        } else if check.has_warning() {
+                            Style::default().fg(Color::Yellow)
+                        } else {
+                            Style::default().fg(Color::Green)
+                        };
+                        let feedback = Paragraph::new(format!(" {}", check.summary(&app.input)))
+                            .style(style);
+                        f.render_widget(feedback, content_chunks[1]);
+                    }
+
+                    if let Some(ref msg) = app.message {
+                        let msg_style = if msg.starts_with("Error") {
+                            Style::default().fg(Color::Red)
+                        } else {
+                            Style::default().fg(Color::Green)
+                        };
+                        let message = Paragraph::new(msg.as_str())
+                            .style(msg_style)
+                            .block(Block::default().borders(Borders::ALL).title("Status"));
+                        f.render_widget(message, content_chunks[2]);
+                    }
+
+                    f.set_cursor_position((
+                        content_chunks[0].x + app.cursor_position as u16 + 1,
+                        content_chunks[0].y + 1,
+                    ));
+
+                    let base_info = if app.detach {
+                        "Detached HEAD: no branch is created".to_string()
+                    } else if let Some(ref base) = app.base_branch {
+                        format!("Base branch: {}", base)
+                    } else {
+                        "Base branch: (current HEAD)".to_string()
+                    };
+
+                    let action = if app.created_worktree.is_some() && app.hook_job.is_none() {
+                        "Press Enter to switch to the new worktree, Ctrl+E to open it in the editor, Ctrl+T for its tmux session, or type another name; Esc to go back"
+                    } else if app.detach {
+                        "Type a tag or commit and press Enter to create, Esc to cancel"
+                    } else {
+                        "Type branch name and press Enter to create, Esc to cancel"
+                    };
+                    let instructions = Paragraph::new(format!("{}\n{}", base_info, action))
+                    .style(Style::default().fg(Color::DarkGray));
+                    f.render_widget(instructions, chunks[2]);
+                }
+                Screen::SelectBaseBranchOption => {
+                    app.base_option_list.render(
+                        f,
+                        chunks[1],
+                        "Select base branch option",
+                        |_, _, spans| Line::from(spans),
+                    );
+
+                    let instructions = Paragraph::new(
+                        "Use ↑/↓ or j/k to navigate, / to filter, Enter to select, Esc to cancel",
+                    )
+                    .style(Style::default().fg(Color::DarkGray));
+                    f.render_widget(instructions, chunks[2]);
+                }
+                Screen::SelectRemote => {
+                    app.remote_list.render(
+                        f,
+                        chunks[1],
+                        "Select remote to fetch from",
+                        |_, _, spans| Line::from(spans),
+                    );
+
+                    let instructions = match app.fetch_job {
+                        Some(ref job) => Paragraph::new(format!("{} (Esc to cancel)", job.status()))
+                            .style(Style::default().fg(Color::Yellow)),
+                        None => Paragraph::new(
+                            "Use ↑/↓ or j/k to navigate, / to filter, Enter to select, Esc to go back",
+                        )
+                        .style(Style::default().fg(Color::DarkGray)),
+                    };
+                    f.render_widget(instructions, chunks[2]);
+                }
+                Screen::SelectRemoteBranch => {
+                    app.remote_branch_list.render(
+                        f,
+                        chunks[1],
+                        "Select remote branch to base your new branch on",
+                        |_, _, spans| Line::from(spans),
+                    );
+
+                    let instructions = Paragraph::new(
+                        "Use ↑/↓ or j/k to navigate, / to filter, Enter to select, Esc to go back",
+                    )
+                    .style(Style::default().fg(Color::DarkGray));
+                    f.render_widget(instructions, chunks[2]);
+                }
+                Screen::ConfirmDelete => {
+                    let worktree = &app.dashboard[app.selected_worktree].worktree;
+                    let branch_name = &worktree.name();
+                    let blocked = app.removal_blocked();
+
+                    let mut warning_text = match app.branch_sync_status {
+                        Some(status) => delete_warning(branch_name, status),
+                        None => detached_warning(worktree, app.head_referenced),
+                    };
+                    if let (Some(merge), Some(base)) =
+                        (app.branch_merge_status, &app.config.default_base_branch)
+                    {
+                        warning_text.push('\n');
+                        warning_text.push_str(&merge_summary(branch_name, merge, base));
+                    }
+                    match app.branch_changes {
+                        Some(ref changes) => {
+                            if let Some(changes_text) = changes_warning(changes) {
+                                warning_text.push('\n');
+                                warning_text.push_str(&changes_text);
+                            }
+                        }
+                        None => warning_text
+                            .push_str("\nCould not check the worktree for uncommitted changes."),
+                    }
+                    if worktree.locked {
+                        warning_text.push_str("\nThe worktree is locked.");
+                    }
+                    let branch_warning = app.branch_warning();
+                    if let Some(ref branch_warning) = branch_warning {
+                        warning_text.push('\n');
+                        warning_text.push_str(branch_warning);
+                    }
+                    if worktree.branch.is_some() {
+                        warning_text.push_str("\n\n");
+                        warning_text.push_str(&delete_mode_line(app.delete_mode));
+                    }
+                    warning_text.push_str("\n\n");
+                    warning_text.push_str(if blocked {
+                        FORCE_DELETE_PROMPT
+                    } else if branch_warning.is_some() {
+                        UNMERGED_DELETE_PROMPT
+                    } else {
+                        DELETE_PROMPT
+                    });
+
+                    let at_risk = match app.branch_sync_status {
+                        Some(status) => status.is_at_risk(),
+                        None => !app.head_referenced,
+                    };
+                    let style = if at_risk || blocked || branch_warning.is_some() {
+                        Style::default().fg(Color::Red)
+                    } else {
+                        Style::default().fg(Color::Yellow)
+                    };
+
+                    let confirm = Paragraph::new(warning_text).style(style).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title("Confirm Delete"),
+                    );
+                    f.render_widget(confirm, chunks[1]);
+
+                    let instructions = match app.removal_job {
+                        Some(ref job) => Paragraph::new(job.status())
+                            .style(Style::default().fg(Color::Yellow)),
+                        None => Paragraph::new(match (blocked || branch_warning.is_some(), worktree.branch.is_some()) {
+                            (true, true) => {
+                                "Press F to force removal, Tab to change what is removed, N or Esc to cancel"
+                            }
+                            (false, true) => {
+                                "Press Y to confirm, Tab to change what is removed, N or Esc to cancel"
+                            }
+                            (true, false) => "Press F to force removal, N or Esc to cancel",
+                            (false, false) => "Press Y to confirm, N or Esc to cancel",
+                        })
+                        .style(Style::default().fg(Color::DarkGray)),
+                    };
+                    f.render_widget(instructions, chunks[2]);
+                }
+                Screen::Cleanup => {
+                    let marked = app.prune_marked.iter().filter(|marked| **marked).count();
+                    let title = format!(
+                        "Merged and stale worktrees ({} of {} selected)",
+                        marked,
+                        app.prune_candidates.len()
+                    );
+                    app.candidate_list.render(f, chunks[1], &title, |i, style, label| {
+                        let candidate = &app.prune_candidates[i];
+                        let checkbox = if app.prune_marked[i] { "[x] " } else { "[ ] " };
+                        let mut spans = vec![Span::styled(checkbox, style)];
+                        spans.extend(label);
+                        spans.push(Span::styled(
+                            format!("  [{}]", candidate.reason),
+                            Style::default().fg(Color::Green),
+                        ));
+                        match candidate.changes {
+                            Some(changes) if changes.blocks_removal() => spans.push(Span::styled(
+                                format!(" [{}]", changes),
+                                Style::default().fg(Color::Red),
+                            )),
+                            Some(_) => {}
+                            None => spans.push(Span::styled(
+                                " [changes unknown]",
+                                Style::default().fg(Color::DarkGray),
+                            )),
+                        }
+                        Line::from(spans)
+                    });
+
+                    let instructions = Paragraph::new(
+                        "Use ↑/↓ or j/k to navigate, / to filter, Space to select, a to select all, Enter to remove, Esc to cancel",
+                    )
+                    .style(Style::default().fg(Color::DarkGray));
+                    f.render_widget(instructions, chunks[2]);
+                }
+                Screen::ConfirmCleanup => {
+                    let marked: Vec<&PruneCandidate> = app.marked_candidates().collect();
+                    let blocked: Vec<&str> = marked
+                        .iter()
+                        .filter(|candidate| candidate.blocks_removal())
+                        .map(|candidate| candidate.branch.as_str())
+                        .collect();
+
+                    let mut text = format!("The worktrees of {} branch(es) will be removed:\n", marked.len());
+                    for candidate in &marked {
+                        text.push_str(&format!("  {} ({})\n", candidate.branch, candidate.reason));
+                    }
+                    if !blocked.is_empty() {
+                        text.push_str(&format!(
+                            "\nWARNING: {} have uncommitted changes that forcing the removal will discard!\n",
+                            blocked.join(", ")
+                        ));
+                    }
+                    let unmerged: Vec<&str> = marked
+                        .iter()
+                        .filter(|candidate| keeps_branch(candidate, app.delete_mode))
+                        .map(|candidate| candidate.branch.as_str())
+                        .collect();
+                    if !unmerged.is_empty() {
+                        text.push_str(&format!(
+                            "\n{} are not known to be merged, so their branches are kept.\n",
+                            unmerged.join(", ")
+                        ));
+                    }
+                    text.push('\n');
+                    text.push_str(&delete_mode_line(app.delete_mode));
+                    text.push_str("\n\n");
+                    text.push_str(if blocked.is_empty() {
+                        CLEANUP_PROMPT
+                    } else {
+                        FORCE_CLEANUP_PROMPT
+                    });
+
+                    let style = if blocked.is_empty() {
+                        Style::default().fg(Color::Yellow)
+                    } else {
+                        Style::default().fg(Color::Red)
+                    };
+
+                    let confirm = Paragraph::new(text).style(style).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title("Confirm Cleanup"),
+                    );
+                    f.render_widget(confirm, chunks[1]);
+
+                    let instructions = match app.removal_job {
+                        Some(ref job) => Paragraph::new(job.status())
+                            .style(Style::default().fg(Color::Yellow)),
+                        None => Paragraph::new(if blocked.is_empty() {
+                            "Press Y to confirm, Tab to change what is removed, N or Esc to go back"
+                        } else {
+                            "Press F to force removal, Tab to change what is removed, N or Esc to go back"
+                        })
+                        .style(Style::default().fg(Color::DarkGray)),
+                    };
+                    f.render_widget(instructions, chunks[2]);
+                }
+                Screen::CleanupSummary => {
+                    let summary = prune::summary(&app.prune_results);
+                    let style = if app.prune_results.iter().any(|result| result.error.is_some()) {
+                        Style::default().fg(Color::Red)
+                    } else {
+                        Style::default().fg(Color::Green)
+                    };
+
+                    let report = Paragraph::new(summary).style(style).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title("Cleanup Summary"),
+                    );
+                    f.render_widget(report, chunks[1]);
+
+                    let instructions = Paragraph::new("Press Enter or Esc to return to the dashboard")
+                        .style(Style::default().fg(Color::DarkGray));
+                    f.render_widget(instructions, chunks[2]);
+                }
+            }
+
+            if let Some(ref msg) = app.message
+                && app.screen == Screen::Dashboard
+            {
+                let msg_style = if msg.starts_with("Error") {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default().fg(Color::Green)
+                };
+                let message = Paragraph::new(msg.as_str())
+                    .style(msg_style)
+                    .block(Block::default().borders(Borders::ALL).title("Status"));
+
+                let popup_area = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(
+                        [
+                            Constraint::Percentage(70),
+                            Constraint::Length(5),
+                            Constraint::Percentage(25),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(main_area)[1];
+
+                // Over the table rather than mixed into it
+                f.render_widget(Clear, popup_area);
+                f.render_widget(message, popup_area);
+            }
+
+            if let Some(log_area) = log_area {
+                // Only the lines that fit, so the latest output stays visible
+                let visible = log_area.height.saturating_sub(2) as usize;
+                let lines: Vec<Line> = app
+                    .log
+                    .iter()
+                    .skip(app.log.len().saturating_sub(visible))
+                    .map(|line| Line::from(line.as_str()))
+                    .collect();
+                let title = match app.hook_job {
+                    Some(ref job) => format!("Log ({} hooks running...)", job.event.name()),
+                    None => "Log".to_string(),
+                };
+
+                let log = Paragraph::new(lines)
+                    .style(Style::default().fg(Color::Gray))
+                    .block(Block::default().borders(Borders::ALL).title(title));
+                f.render_widget(log, log_area);
+            }
+        })?;
+
+        events.observe(app.screen(), app.is_busy());
+        if let Some(Event::Key(key)) = events.next(Duration::from_millis(100))? {
+            if key.code == KeyCode::Char('c')
+                && key.modifiers.contains(event::KeyModifiers::CONTROL)
+            {
+                break;
+            }
+
+            if app.handle_key_event(key)? {
+                break;
+            }
+
+            if let Some((name, mut command)) = app.launch.take() {
+                match suspended(terminal, || command.status()) {
+                    Ok(status) if status.success() => {}
+                    // Shells pass on the status of their last command, so
+                    // this isn't necessarily a failure
+                    Ok(status) => app.message = Some(format!("{} exited with {}", name, status)),
+                    Err(e) => app.message = Some(format!("Error: Failed to run {}: {}", name, e)),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the subcommand given on the command line, or the TUI without one.
+pub fn run() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(command) => cli::run(command, cli.json, cli.cd_file)?,
+        None => {
+            let repo_root = find_git_root()?;
+            let config = load_or_create_config(&repo_root)?;
+            if let Some(worktree) = run_main_app(repo_root, config)? {
+                // Without the shell wrapper there's no changing the shell's
+                // directory, so at least say where the worktree is
+                match cli.cd_file {
+                    Some(cd_file) => shell::write_cd_file(&cd_file, &worktree)?,
+                    None => println!("{}", worktree.display()),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
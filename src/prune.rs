@@ -0,0 +1,116 @@
+use std::fmt;
+
+use crate::{
+    sync::{MergeStatus, SyncStatus},
+    worktree::{Worktree, WorktreeChanges},
+};
+
+/// Why a worktree is considered finished and offered for cleanup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruneReason {
+    Merged,
+    SquashMerged,
+    /// The remote branch was deleted, usually after the pull request was
+    /// merged, and `fetch --prune` removed its remote-tracking ref
+    UpstreamGone,
+}
+
+impl PruneReason {
+    /// A merge into the base is the stronger signal, so it wins when a
+    /// branch is both merged and has lost its upstream.
+    pub fn of(sync: Option<SyncStatus>, merge: Option<MergeStatus>) -> Option<Self> {
+        match (merge, sync) {
+            (Some(MergeStatus::Merged), _) => Some(PruneReason::Merged),
+            (Some(MergeStatus::SquashMerged), _) => Some(PruneReason::SquashMerged),
+            (_, Some(SyncStatus::UpstreamGone)) => Some(PruneReason::UpstreamGone),
+            _ => None,
+        }
+    }
+
+    /// Stable identifier used in JSON output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PruneReason::Merged => "merged",
+            PruneReason::SquashMerged => "squash_merged",
+            PruneReason::UpstreamGone => "upstream_gone",
+        }
+    }
+}
+
+impl fmt::Display for PruneReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PruneReason::Merged => write!(f, "merged"),
+            PruneReason::SquashMerged => write!(f, "squash-merged"),
+            PruneReason::UpstreamGone => write!(f, "upstream gone"),
+        }
+    }
+}
+
+/// A gitsy worktree whose branch looks done with.
+#[derive(Debug, Clone)]
+pub struct PruneCandidate {
+    pub branch: String,
+    pub worktree: Worktree,
+    pub reason: PruneReason,
+    /// Uncommitted state of the worktree, or `None` if it couldn't be inspected
+    pub changes: Option<WorktreeChanges>,
+    pub sync: Option<SyncStatus>,
+    pub merge: Option<MergeStatus>,
+}
+
+impl PruneCandidate {
+    /// Whether `git worktree remove` needs `--force` for this worktree,
+    /// because of uncommitted changes or a lock.
+    pub fn blocks_removal(&self) -> bool {
+        self.worktree.locked || self.changes.is_some_and(|changes| changes.blocks_removal())
+    }
+}
+
+/// What happened to one worktree in a batch removal.
+#[derive(Debug, Clone)]
+pub struct PruneResult {
+    pub branch: String,
+    /// The failure message, or `None` if the worktree was removed
+    pub error: Option<String>,
+    /// Why the `post_delete` hooks failed after the worktree was removed
+    pub hook_error: Option<String>,
+    /// Only the worktree was removed, as the branch is not known to be
+    /// merged
+    pub kept_branch: bool,
+}
+
+/// One line per failure after a count of removed worktrees, shared by the
+/// cleanup screen and `gitsy prune-merged`.
+pub fn summary(results: &[PruneResult]) -> String {
+    let removed = results
+        .iter()
+        .filter(|result| result.error.is_none())
+        .count();
+    let mut text = format!("Removed {} of {} worktree(s).", removed, results.len());
+
+    for result in results {
+        if let Some(ref error) = result.error {
+            text.push_str(&format!(
+                "\nFailed to remove '{}': {}",
+                result.branch,
+                error.trim()
+            ));
+        }
+        if result.kept_branch && result.error.is_none() {
+            text.push_str(&format!(
+                "\nKept branch '{}', which is not known to be merged",
+                result.branch
+            ));
+        }
+        if let Some(ref hook_error) = result.hook_error {
+            text.push_str(&format!(
+                "\nRemoved '{}', but {}",
+                result.branch,
+                hook_error.trim()
+            ));
+        }
+    }
+
+    text
+}
@@ -0,0 +1,331 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// What to do when a file to copy or symlink already exists in the new
+/// worktree, typically because it is tracked after all.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictPolicy {
+    /// Keep the worktree's version
+    #[default]
+    Skip,
+    /// Replace it with the repository root's version
+    Overwrite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    Copied,
+    Symlinked,
+    Skipped,
+    Failed,
+}
+
+/// The outcome for one path matched by `copy` or `symlink`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SharedFile {
+    /// Relative to both the repository root and the worktree
+    pub path: PathBuf,
+    pub action: FileAction,
+    /// Why the file was skipped or failed
+    pub reason: Option<String>,
+}
+
+impl SharedFile {
+    fn new(path: &Path, action: FileAction, reason: Option<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            action,
+            reason,
+        }
+    }
+}
+
+impl std::fmt::Display for SharedFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self.action {
+            FileAction::Copied => "Copied",
+            FileAction::Symlinked => "Symlinked",
+            FileAction::Skipped => "Skipped",
+            FileAction::Failed => "Failed",
+        };
+        write!(f, "{} {}", action, self.path.display())?;
+        if let Some(ref reason) = self.reason {
+            write!(f, ": {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Brings the files matching the `copy` and `symlink` globs from `repo_root`
+/// into `worktree`. Copies are made first, so a path matched by both lists
+/// is copied. Nothing under `.git` or `worktree_root` is ever matched, so
+/// globs like `**/.env` don't pick up files from other worktrees.
+pub fn materialize(
+    repo_root: &Path,
+    worktree: &Path,
+    worktree_root: &Path,
+    copy: &[String],
+    symlink: &[String],
+    policy: ConflictPolicy,
+) -> Vec<SharedFile> {
+    let mut report = Vec::new();
+    let mut seen = HashSet::new();
+
+    for (patterns, action) in [(copy, FileAction::Copied), (symlink, FileAction::Symlinked)] {
+        for pattern in patterns {
+            let matches = match find_matches(repo_root, worktree_root, pattern) {
+                Ok(matches) => matches,
+                Err(e) => {
+                    report.push(SharedFile::new(
+                        Path::new(pattern),
+                        FileAction::Failed,
+                        Some(format!("{:#}", e)),
+                    ));
+                    continue;
+                }
+            };
+
+            for path in matches {
+                if !seen.insert(path.clone()) {
+                    continue;
+                }
+
+                let source = repo_root.join(&path);
+                let target = worktree.join(&path);
+                let result = if fs::symlink_metadata(&target).is_ok() {
+                    match policy {
+                        ConflictPolicy::Skip => {
+                            report.push(SharedFile::new(
+                                &path,
+                                FileAction::Skipped,
+                                Some("already exists in the worktree".to_string()),
+                            ));
+                            continue;
+                        }
+                        ConflictPolicy::Overwrite => {
+                            remove(&target).and_then(|()| materialize_one(&source, &target, action))
+                        }
+                    }
+                } else {
+                    materialize_one(&source, &target, action)
+                };
+
+                report.push(match result {
+                    Ok(()) => SharedFile::new(&path, action, None),
+                    Err(e) => SharedFile::new(&path, FileAction::Failed, Some(e.to_string())),
+                });
+            }
+        }
+    }
+
+    report
+}
+
+/// Paths relative to `repo_root` that match `pattern`, in glob order.
+fn find_matches(repo_root: &Path, worktree_root: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let relative = Path::new(pattern);
+    if relative.is_absolute()
+        || relative
+            .components()
+            .any(|component| component == Component::ParentDir)
+    {
+        return Err(anyhow::anyhow!(
+            "'{}' must be relative to the repository root",
+            pattern
+        ));
+    }
+
+    let root = glob::Pattern::escape(&repo_root.to_string_lossy());
+    let full_pattern = format!("{}/{}", root.trim_end_matches('/'), pattern);
+    let paths = glob::glob(&full_pattern).with_context(|| format!("Invalid glob '{}'", pattern))?;
+
+    let mut matches = Vec::new();
+    for path in paths {
+        let path = path?;
+        if path.starts_with(worktree_root) {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(repo_root) else {
+            continue;
+        };
+        if relative.starts_with(".git") {
+            continue;
+        }
+        matches.push(relative.to_path_buf());
+    }
+
+    Ok(matches)
+}
+
+fn materialize_one(source: &Path, target: &Path, action: FileAction) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match action {
+        FileAction::Symlinked => symlink(source, target),
+        _ => copy_recursive(source, target),
+    }
+}
+
+fn copy_recursive(source: &Path, target: &Path) -> io::Result<()> {
+    if source.is_dir() {
+        fs::create_dir_all(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(source, target).map(|_| ())
+    }
+}
+
+fn remove(target: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(target)?;
+    if metadata.is_dir() {
+        fs::remove_dir_all(target)
+    } else {
+        fs::remove_file(target)
+    }
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    if source.is_dir() {
+        std::os::windows::fs::symlink_dir(source, target)
+    } else {
+        std::os::windows::fs::symlink_file(source, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A repository root with `.env` and `config/local.json`, a worktree
+    /// that already has its own `.env`, and where worktrees are kept.
+    fn dirs() -> (TempDir, PathBuf, PathBuf, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let repo_root = dir.path().join("repo");
+        let worktree_root = dir.path().join("wt");
+        let worktree = worktree_root.join("feature");
+        fs::create_dir_all(repo_root.join("config")).unwrap();
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(repo_root.join(".env"), "SECRET=root\n").unwrap();
+        fs::write(repo_root.join("config/local.json"), "{}\n").unwrap();
+        fs::write(worktree.join(".env"), "SECRET=worktree\n").unwrap();
+        (dir, repo_root, worktree_root, worktree)
+    }
+
+    fn actions(report: &[SharedFile]) -> Vec<(String, FileAction)> {
+        report
+            .iter()
+            .map(|file| (file.path.display().to_string(), file.action))
+            .collect()
+    }
+
+    #[test]
+    fn skips_existing_files() {
+        let (_dir, repo_root, worktree_root, worktree) = dirs();
+
+        let report = materialize(
+            &repo_root,
+            &worktree,
+            &worktree_root,
+            &[".env".to_string(), "config/*.json".to_string()],
+            &[],
+            ConflictPolicy::Skip,
+        );
+
+        assert_eq!(
+            actions(&report),
+            [
+                (".env".to_string(), FileAction::Skipped),
+                ("config/local.json".to_string(), FileAction::Copied),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(worktree.join(".env")).unwrap(),
+            "SECRET=worktree\n"
+        );
+        assert!(worktree.join("config/local.json").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overwrites_existing_files() {
+        let (_dir, repo_root, worktree_root, worktree) = dirs();
+
+        let report = materialize(
+            &repo_root,
+            &worktree,
+            &worktree_root,
+            &[],
+            &[".env".to_string()],
+            ConflictPolicy::Overwrite,
+        );
+
+        assert_eq!(
+            actions(&report),
+            [(".env".to_string(), FileAction::Symlinked)]
+        );
+        let target = worktree.join(".env");
+        assert!(fs::symlink_metadata(&target).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "SECRET=root\n");
+    }
+
+    #[test]
+    fn copies_paths_matched_twice_once() {
+        let (_dir, repo_root, worktree_root, worktree) = dirs();
+        fs::remove_file(worktree.join(".env")).unwrap();
+
+        let report = materialize(
+            &repo_root,
+            &worktree,
+            &worktree_root,
+            &[".env".to_string()],
+            &[".env".to_string()],
+            ConflictPolicy::Skip,
+        );
+
+        assert_eq!(actions(&report), [(".env".to_string(), FileAction::Copied)]);
+        assert!(
+            !fs::symlink_metadata(worktree.join(".env"))
+                .unwrap()
+                .is_symlink()
+        );
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_repository() {
+        let (_dir, repo_root, worktree_root, worktree) = dirs();
+
+        let report = materialize(
+            &repo_root,
+            &worktree,
+            &worktree_root,
+            &["../secrets".to_string()],
+            &[],
+            ConflictPolicy::Overwrite,
+        );
+
+        assert_eq!(
+            actions(&report),
+            [("../secrets".to_string(), FileAction::Failed)]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// A `gitsy` function that runs the binary with `--cd-file` and changes into
+/// whatever worktree it wrote there.
+const POSIX_WRAPPER: &str = r#"gitsy() {
+    local cd_file exit_code
+    cd_file="$(mktemp "${TMPDIR:-/tmp}/gitsy.XXXXXX")" || return
+    command gitsy --cd-file "$cd_file" "$@"
+    exit_code=$?
+    if [ -s "$cd_file" ]; then
+        cd -- "$(cat "$cd_file")" || exit_code=$?
+    fi
+    rm -f -- "$cd_file"
+    return $exit_code
+}
+"#;
+
+const FISH_WRAPPER: &str = r#"function gitsy --wraps gitsy --description 'Git worktree manager'
+    set -l cd_file (mktemp (set -q TMPDIR; and echo $TMPDIR; or echo /tmp)/gitsy.XXXXXX); or return
+    command gitsy --cd-file $cd_file $argv
+    set -l exit_code $status
+    if test -s $cd_file
+        cd (cat $cd_file); or set exit_code $status
+    end
+    rm -f -- $cd_file
+    return $exit_code
+end
+"#;
+
+impl Shell {
+    /// The wrapper to `eval` from the shell's startup file.
+    pub fn wrapper(self) -> &'static str {
+        match self {
+            Shell::Bash | Shell::Zsh => POSIX_WRAPPER,
+            Shell::Fish => FISH_WRAPPER,
+        }
+    }
+
+    /// How to load the wrapper, printed as a comment above it.
+    pub fn setup_hint(self) -> &'static str {
+        match self {
+            Shell::Bash => "# Add to ~/.bashrc:\n#   eval \"$(gitsy init bash)\"",
+            Shell::Zsh => "# Add to ~/.zshrc:\n#   eval \"$(gitsy init zsh)\"",
+            Shell::Fish => "# Add to ~/.config/fish/config.fish:\n#   gitsy init fish | source",
+        }
+    }
+}
+
+/// Hands the worktree to change into over to the shell wrapper. `cd_file`
+/// may also be a file descriptor path such as `/dev/fd/3`.
+pub fn write_cd_file(cd_file: &Path, worktree: &Path) -> Result<()> {
+    fs::write(cd_file, worktree.to_string_lossy().as_bytes())
+        .with_context(|| format!("Failed to write {}", cd_file.display()))
+}
@@ -0,0 +1,396 @@
+use anyhow::{Context, Result};
+use git2::{BranchType, Repository};
+use std::fmt;
+
+/// How a local branch relates to its upstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStatus {
+    Synced,
+    Ahead(usize),
+    Behind(usize),
+    Diverged(usize, usize),
+    /// The branch has never been pushed or has no tracking configuration
+    NoUpstream,
+    /// An upstream is configured but its remote-tracking ref is gone,
+    /// typically because the remote branch was deleted and fetched with `--prune`
+    UpstreamGone,
+}
+
+impl SyncStatus {
+    pub fn of_branch(repo: &Repository, branch_name: &str) -> Result<Self> {
+        let local_branch = repo.find_branch(branch_name, BranchType::Local)?;
+        let local_oid = local_branch
+            .get()
+            .target()
+            .context("Failed to get local branch target")?;
+
+        let upstream = match local_branch.upstream() {
+            Ok(upstream) => upstream,
+            Err(_) => {
+                let refname = local_branch
+                    .get()
+                    .name()
+                    .context("Branch name is not valid UTF-8")?;
+                // The upstream name comes from config alone, so it still
+                // resolves after the remote-tracking ref has been pruned
+                return Ok(if repo.branch_upstream_name(refname).is_ok() {
+                    SyncStatus::UpstreamGone
+                } else {
+                    SyncStatus::NoUpstream
+                });
+            }
+        };
+
+        let upstream_oid = upstream
+            .get()
+            .target()
+            .context("Failed to get upstream branch target")?;
+
+        let (ahead, behind) = repo.graph_ahead_behind(local_oid, upstream_oid)?;
+        Ok(Self::from_counts(ahead, behind))
+    }
+
+    pub fn from_counts(ahead: usize, behind: usize) -> Self {
+        match (ahead, behind) {
+            (0, 0) => SyncStatus::Synced,
+            (ahead, 0) => SyncStatus::Ahead(ahead),
+            (0, behind) => SyncStatus::Behind(behind),
+            (ahead, behind) => SyncStatus::Diverged(ahead, behind),
+        }
+    }
+
+    /// Whether the branch may have commits that exist nowhere else, so
+    /// deleting it deserves a warning. Being behind loses nothing.
+    pub fn is_at_risk(&self) -> bool {
+        !matches!(self, SyncStatus::Synced | SyncStatus::Behind(_))
+    }
+
+    pub fn ahead(&self) -> usize {
+        match *self {
+            SyncStatus::Ahead(ahead) | SyncStatus::Diverged(ahead, _) => ahead,
+            _ => 0,
+        }
+    }
+
+    pub fn behind(&self) -> usize {
+        match *self {
+            SyncStatus::Behind(behind) | SyncStatus::Diverged(_, behind) => behind,
+            _ => 0,
+        }
+    }
+
+    /// Stable identifier used in JSON and porcelain output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncStatus::Synced => "synced",
+            SyncStatus::Ahead(_) => "ahead",
+            SyncStatus::Behind(_) => "behind",
+            SyncStatus::Diverged(_, _) => "diverged",
+            SyncStatus::NoUpstream => "no_upstream",
+            SyncStatus::UpstreamGone => "upstream_gone",
+        }
+    }
+}
+
+impl fmt::Display for SyncStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncStatus::Synced => write!(f, "in sync"),
+            SyncStatus::Ahead(ahead) => write!(f, "{} ahead", ahead),
+            SyncStatus::Behind(behind) => write!(f, "{} behind", behind),
+            SyncStatus::Diverged(ahead, behind) => {
+                write!(f, "diverged ({} ahead, {} behind)", ahead, behind)
+            }
+            SyncStatus::NoUpstream => write!(f, "no upstream"),
+            SyncStatus::UpstreamGone => write!(f, "upstream gone"),
+        }
+    }
+}
+
+/// Whether a branch's work has landed in the base branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStatus {
+    /// The branch tip is reachable from the base
+    Merged,
+    /// Not reachable, but merging the branch into the base would change
+    /// nothing, as after a squash or rebase merge
+    SquashMerged,
+    NotMerged,
+    /// The branch has no work of its own yet: it hasn't moved since it was
+    /// created, or its commits change nothing
+    NoChanges,
+}
+
+impl MergeStatus {
+    pub fn of_branch(repo: &Repository, branch_name: &str, base: &str) -> Result<Self> {
+        let branch_commit = repo
+            .find_branch(branch_name, BranchType::Local)?
+            .get()
+            .peel_to_commit()?;
+        let base_commit = repo
+            .revparse_single(base)
+            .with_context(|| format!("Base branch '{}' not found", base))?
+            .peel_to_commit()?;
+
+        if branch_commit.id() == base_commit.id() {
+            return Ok(MergeStatus::NoChanges);
+        }
+        if repo.graph_descendant_of(base_commit.id(), branch_commit.id())? {
+            // A new branch is just as reachable as a merged one. It never
+            // moved, and sits on a commit the base itself was at, whereas a
+            // branch checked out from already merged work only got into the
+            // base through a merge
+            if never_moved(repo, branch_name, branch_commit.id())
+                && on_first_parent_chain(repo, base_commit.id(), branch_commit.id())?
+            {
+                return Ok(MergeStatus::NoChanges);
+            }
+            return Ok(MergeStatus::Merged);
+        }
+
+        let merge_base = repo.merge_base(base_commit.id(), branch_commit.id())?;
+        let fork_tree = repo.find_commit(merge_base)?.tree()?;
+        let branch_tree = branch_commit.tree()?;
+        if fork_tree.id() == branch_tree.id() {
+            return Ok(MergeStatus::NoChanges);
+        }
+
+        let index = repo.merge_commits(&base_commit, &branch_commit, None)?;
+        if index.has_conflicts() {
+            return Ok(MergeStatus::NotMerged);
+        }
+
+        let base_tree = base_commit.tree()?;
+        let diff = repo.diff_tree_to_index(Some(&base_tree), Some(&index), None)?;
+        if diff.deltas().len() == 0 {
+            Ok(MergeStatus::SquashMerged)
+        } else {
+            Ok(MergeStatus::NotMerged)
+        }
+    }
+
+    pub fn is_merged(&self) -> bool {
+        matches!(self, MergeStatus::Merged | MergeStatus::SquashMerged)
+    }
+
+    /// Stable identifier used in JSON and porcelain output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MergeStatus::Merged => "merged",
+            MergeStatus::SquashMerged => "squash_merged",
+            MergeStatus::NotMerged => "not_merged",
+            MergeStatus::NoChanges => "no_changes",
+        }
+    }
+}
+
+/// Whether the branch still points where it was created, according to its
+/// reflog. Without a reflog there's no telling, so it counts as moved.
+fn never_moved(repo: &Repository, branch_name: &str, tip: git2::Oid) -> bool {
+    let Ok(reflog) = repo.reflog(&format!("refs/heads/{}", branch_name)) else {
+        return false;
+    };
+    !reflog.is_empty() && reflog.iter().all(|entry| entry.id_new() == tip)
+}
+
+/// Whether `base` was itself at `tip` at some point, following only first
+/// parents, rather than getting it from a merge.
+fn on_first_parent_chain(repo: &Repository, base: git2::Oid, tip: git2::Oid) -> Result<bool> {
+    let mut walk = repo.revwalk()?;
+    walk.simplify_first_parent()?;
+    walk.push(base)?;
+    // Nothing older than the tip can be the tip
+    for parent in repo.find_commit(tip)?.parent_ids() {
+        walk.hide(parent)?;
+    }
+    for id in walk {
+        if id? == tip {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+impl fmt::Display for MergeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeStatus::Merged => write!(f, "merged"),
+            MergeStatus::SquashMerged => write!(f, "squash-merged"),
+            MergeStatus::NotMerged => write!(f, "not merged"),
+            MergeStatus::NoChanges => write!(f, "no changes"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, RepositoryInitOptions, Signature};
+    use tempfile::TempDir;
+
+    fn repository() -> (TempDir, Repository) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init_opts(
+            dir.path(),
+            RepositoryInitOptions::new().initial_head("main"),
+        )
+        .unwrap();
+        commit(&repo, "HEAD", "README.md", "# Demo\n", &[]);
+        (dir, repo)
+    }
+
+    /// Commits `content` as `file` to `refname`, on top of the tree of the
+    /// first parent.
+    fn commit(repo: &Repository, refname: &str, file: &str, content: &str, parents: &[Oid]) -> Oid {
+        let parent_tree = parents
+            .first()
+            .map(|parent| repo.find_commit(*parent).unwrap().tree().unwrap());
+        let mut builder = repo.treebuilder(parent_tree.as_ref()).unwrap();
+        let blob = repo.blob(content.as_bytes()).unwrap();
+        builder.insert(file, blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+
+        let signature = Signature::now("Gitsy", "gitsy@example.com").unwrap();
+        let parents: Vec<_> = parents
+            .iter()
+            .map(|id| repo.find_commit(*id).unwrap())
+            .collect();
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(Some(refname), &signature, &signature, file, &tree, &parents)
+            .unwrap()
+    }
+
+    fn head(repo: &Repository) -> Oid {
+        repo.head().unwrap().peel_to_commit().unwrap().id()
+    }
+
+    fn status(repo: &Repository, branch: &str) -> MergeStatus {
+        MergeStatus::of_branch(repo, branch, "main").unwrap()
+    }
+
+    #[test]
+    fn new_branch_has_no_changes() {
+        let (_dir, repo) = repository();
+        let main = repo.find_commit(head(&repo)).unwrap();
+        repo.branch("feature", &main, false).unwrap();
+        assert_eq!(status(&repo, "feature"), MergeStatus::NoChanges);
+
+        // Still nothing of its own once main has moved on
+        commit(&repo, "HEAD", "CHANGELOG.md", "# Changes\n", &[main.id()]);
+        assert_eq!(status(&repo, "feature"), MergeStatus::NoChanges);
+    }
+
+    #[test]
+    fn branch_with_only_empty_commits_has_no_changes() {
+        let (_dir, repo) = repository();
+        let main = head(&repo);
+        repo.branch("feature", &repo.find_commit(main).unwrap(), false)
+            .unwrap();
+        commit(
+            &repo,
+            "refs/heads/feature",
+            "README.md",
+            "# Demo\n",
+            &[main],
+        );
+        commit(&repo, "HEAD", "CHANGELOG.md", "# Changes\n", &[main]);
+
+        assert_eq!(status(&repo, "feature"), MergeStatus::NoChanges);
+    }
+
+    #[test]
+    fn merged_branch_is_merged() {
+        let (_dir, repo) = repository();
+        let main = head(&repo);
+        repo.branch("feature", &repo.find_commit(main).unwrap(), false)
+            .unwrap();
+        let feature = commit(
+            &repo,
+            "refs/heads/feature",
+            "feature.txt",
+            "done\n",
+            &[main],
+        );
+        assert_eq!(status(&repo, "feature"), MergeStatus::NotMerged);
+
+        commit(&repo, "HEAD", "feature.txt", "done\n", &[main, feature]);
+        assert_eq!(status(&repo, "feature"), MergeStatus::Merged);
+    }
+
+    #[test]
+    fn branch_checked_out_from_merged_work_is_merged() {
+        let (_dir, repo) = repository();
+        let main = head(&repo);
+        let feature = commit(&repo, "refs/heads/done", "feature.txt", "done\n", &[main]);
+        commit(&repo, "HEAD", "feature.txt", "done\n", &[main, feature]);
+
+        // As a tracking checkout of the merged remote branch would make it
+        repo.branch("feature", &repo.find_commit(feature).unwrap(), false)
+            .unwrap();
+        assert_eq!(status(&repo, "feature"), MergeStatus::Merged);
+    }
+
+    #[test]
+    fn squash_merged_branch_is_squash_merged() {
+        let (_dir, repo) = repository();
+        let main = head(&repo);
+        repo.branch("feature", &repo.find_commit(main).unwrap(), false)
+            .unwrap();
+        let first = commit(
+            &repo,
+            "refs/heads/feature",
+            "feature.txt",
+            "draft\n",
+            &[main],
+        );
+        commit(
+            &repo,
+            "refs/heads/feature",
+            "feature.txt",
+            "done\n",
+            &[first],
+        );
+        // The squashed commit lands on main after someone else's work
+        let other = commit(&repo, "HEAD", "CHANGELOG.md", "# Changes\n", &[main]);
+        commit(&repo, "HEAD", "feature.txt", "done\n", &[other]);
+
+        assert_eq!(status(&repo, "feature"), MergeStatus::SquashMerged);
+    }
+
+    #[test]
+    fn partly_squashed_branch_is_not_merged() {
+        let (_dir, repo) = repository();
+        let main = head(&repo);
+        repo.branch("feature", &repo.find_commit(main).unwrap(), false)
+            .unwrap();
+        let first = commit(
+            &repo,
+            "refs/heads/feature",
+            "feature.txt",
+            "done\n",
+            &[main],
+        );
+        commit(&repo, "refs/heads/feature", "more.txt", "later\n", &[first]);
+        commit(&repo, "HEAD", "feature.txt", "done\n", &[main]);
+
+        assert_eq!(status(&repo, "feature"), MergeStatus::NotMerged);
+    }
+
+    #[test]
+    fn conflicting_branch_is_not_merged() {
+        let (_dir, repo) = repository();
+        let main = head(&repo);
+        repo.branch("feature", &repo.find_commit(main).unwrap(), false)
+            .unwrap();
+        commit(
+            &repo,
+            "refs/heads/feature",
+            "README.md",
+            "# Feature\n",
+            &[main],
+        );
+        commit(&repo, "HEAD", "README.md", "# Main\n", &[main]);
+
+        assert_eq!(status(&repo, "feature"), MergeStatus::NotMerged);
+    }
+}
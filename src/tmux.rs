@@ -0,0 +1,254 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    path::Path,
+    process::{Command, Output},
+};
+
+/// The `[tmux]` table of .gitsy.toml: one tmux session per worktree.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TmuxConfig {
+    /// Create a session for every new worktree and kill it on deletion
+    #[serde(default)]
+    pub enabled: bool,
+    /// Talk to the tmux server on this socket name (`tmux -L`) instead of
+    /// the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket: Option<String>,
+    /// Windows to open in new sessions; a single shell window when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<TmuxWindow>,
+}
+
+/// A window of a worktree's session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TmuxWindow {
+    pub name: String,
+    /// A command to type into each pane; an empty string leaves a plain shell
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub panes: Vec<String>,
+    /// A tmux layout such as `main-vertical` or `even-horizontal`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+}
+
+impl TmuxConfig {
+    pub fn is_empty(&self) -> bool {
+        !self.enabled && self.socket.is_none() && self.windows.is_empty()
+    }
+
+    /// tmux uses `.` and `:` in target names, so they can't appear in a
+    /// session name. A name that had them gets a hash of the branch name,
+    /// so `a.b` and `a_b` don't end up sharing a session.
+    pub fn session_name(branch_name: &str) -> String {
+        if !branch_name.contains(['.', ':']) {
+            return branch_name.to_string();
+        }
+        format!(
+            "{}-{:08x}",
+            branch_name.replace(['.', ':'], "_"),
+            fnv1a(branch_name)
+        )
+    }
+
+    fn tmux(&self) -> Command {
+        let mut command = Command::new("tmux");
+        if let Some(ref socket) = self.socket {
+            command.arg("-L").arg(socket);
+        }
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Result<Output> {
+        let output = self
+            .tmux()
+            .args(args)
+            .output()
+            .context("Failed to execute tmux")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "tmux {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(output)
+    }
+
+    pub fn has_session(&self, session: &str) -> bool {
+        // `=` makes tmux match the name exactly rather than as a prefix
+        self.run(&["has-session", "-t", &format!("={}", session)])
+            .is_ok()
+    }
+
+    /// Creates the session for `branch_name` in `worktree_path` with the
+    /// configured windows, unless it already exists. Returns its name.
+    pub fn ensure_session(&self, branch_name: &str, worktree_path: &Path) -> Result<String> {
+        let session = Self::session_name(branch_name);
+        if self.has_session(&session) {
+            return Ok(session);
+        }
+
+        let dir = worktree_path.to_string_lossy();
+        let mut new_session = vec!["new-session", "-d", "-s", &session, "-c", &dir];
+        if let Some(window) = self.windows.first() {
+            new_session.extend(["-n", &window.name]);
+        }
+        self.run(&new_session)?;
+
+        for (i, window) in self.windows.iter().enumerate() {
+            let target = format!("={}:{}", session, window.name);
+            if i > 0 {
+                let session_target = format!("={}:", session);
+                self.run(&[
+                    "new-window",
+                    "-t",
+                    &session_target,
+                    "-n",
+                    &window.name,
+                    "-c",
+                    &dir,
+                ])?;
+            }
+
+            for (pane, command) in window.panes.iter().enumerate() {
+                if pane > 0 {
+                    self.run(&["split-window", "-t", &target, "-c", &dir])?;
+                }
+                if !command.is_empty() {
+                    // Typed rather than passed to tmux, so the pane keeps its
+                    // shell once the command exits
+                    self.run(&["send-keys", "-t", &target, command, "Enter"])?;
+                }
+            }
+
+            if let Some(ref layout) = window.layout {
+                self.run(&["select-layout", "-t", &target, layout])?;
+            }
+        }
+
+        if let Some(window) = self.windows.first() {
+            self.run(&[
+                "select-window",
+                "-t",
+                &format!("={}:{}", session, window.name),
+            ])?;
+        }
+
+        Ok(session)
+    }
+
+    /// The command that puts the terminal into `session`: `switch-client`
+    /// from inside the same tmux server, `attach-session` otherwise.
+    pub fn attach_command(&self, session: &str) -> Command {
+        let target = format!("={}", session);
+        let mut command = self.tmux();
+        if self.socket.is_none() && env::var_os("TMUX").is_some() {
+            command.args(["switch-client", "-t", &target]);
+        } else {
+            // Nesting a client of another server inside tmux is fine
+            command.env_remove("TMUX");
+            command.args(["attach-session", "-t", &target]);
+        }
+        command
+    }
+
+    /// Kills the session for `branch_name` if there is one. The session
+    /// gitsy itself runs in is left alone.
+    pub fn kill_session(&self, branch_name: &str) -> Result<()> {
+        let session = Self::session_name(branch_name);
+        if !self.has_session(&session) || self.is_current_session(&session) {
+            return Ok(());
+        }
+
+        self.run(&["kill-session", "-t", &format!("={}", session)])?;
+        Ok(())
+    }
+
+    fn is_current_session(&self, session: &str) -> bool {
+        if self.socket.is_some() || env::var_os("TMUX").is_none() {
+            return false;
+        }
+
+        self.run(&["display-message", "-p", "#{session_name}"])
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == session)
+    }
+}
+
+/// A hash that stays the same across Rust releases, unlike std's, since
+/// sessions outlive the gitsy that named them.
+fn fnv1a(text: &str) -> u32 {
+    text.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+    use tempfile::TempDir;
+
+    #[test]
+    fn session_names_stay_unique() {
+        assert_eq!(TmuxConfig::session_name("feature/login"), "feature/login");
+        let dotted = TmuxConfig::session_name("a.b");
+        assert!(dotted.starts_with("a_b-"));
+        assert!(!dotted.contains(['.', ':']));
+        assert_ne!(dotted, TmuxConfig::session_name("a_b"));
+        assert_ne!(dotted, TmuxConfig::session_name("a:b"));
+        assert_eq!(dotted, TmuxConfig::session_name("a.b"));
+    }
+
+    /// Runs against a tmux server of its own. Needs tmux installed, so it
+    /// only runs with `cargo test -- --ignored`.
+    #[test]
+    #[ignore = "needs tmux"]
+    fn creates_and_reattaches_session() {
+        let dir = TempDir::new().unwrap();
+        let config = TmuxConfig {
+            enabled: true,
+            socket: Some(format!("gitsy-test-{}", process::id())),
+            windows: vec![TmuxWindow {
+                name: "editor".to_string(),
+                panes: vec![String::new(), String::new()],
+                layout: Some("even-horizontal".to_string()),
+            }],
+        };
+
+        let session = config.ensure_session("release.1", dir.path()).unwrap();
+        let created = config.has_session(&session);
+        // Asking again finds the same session rather than making another
+        let reattached = config.ensure_session("release.1", dir.path());
+        let sessions = config.run(&["list-sessions", "-F", "#{session_name}"]);
+        let attach: Vec<String> = config
+            .attach_command(&session)
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        config.kill_session("release.1").unwrap();
+        let killed = !config.has_session(&session);
+        let _ = config.run(&["kill-server"]);
+
+        assert!(created);
+        assert_eq!(reattached.unwrap(), session);
+        assert_eq!(
+            String::from_utf8_lossy(&sessions.unwrap().stdout).trim(),
+            session
+        );
+        assert_eq!(
+            attach,
+            [
+                "-L",
+                config.socket.as_deref().unwrap(),
+                "attach-session",
+                "-t",
+                &format!("={}", session)
+            ]
+        );
+        assert!(killed);
+    }
+}
@@ -0,0 +1,287 @@
+use anyhow::Result;
+use git2::{DescribeOptions, Oid, Repository, Status, StatusOptions};
+use serde::Serialize;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// A single entry from `git worktree list --porcelain`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Worktree {
+    pub path: PathBuf,
+    /// Short branch name, or `None` for detached and bare worktrees
+    pub branch: Option<String>,
+    /// The checked-out commit, or `None` for bare worktrees
+    pub head: Option<String>,
+    pub bare: bool,
+    pub detached: bool,
+    pub locked: bool,
+    pub prunable: bool,
+    /// `git describe --tags` of a detached HEAD, or `None` when no tag leads
+    /// to it; filled in by `describe_detached`
+    pub describe: Option<String>,
+}
+
+impl Worktree {
+    /// The branch, or the directory name for detached and bare worktrees.
+    /// Used in messages and as the worktree's tmux session name.
+    pub fn name(&self) -> String {
+        match self.branch {
+            Some(ref branch) => branch.clone(),
+            None => self
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| self.path.display().to_string()),
+        }
+    }
+
+    pub fn short_head(&self) -> Option<&str> {
+        self.head.as_deref().map(|head| &head[..head.len().min(7)])
+    }
+
+    /// The branch, or for a detached HEAD its short SHA and describe output,
+    /// e.g. `1a2b3c4 (v1.2-3-g1a2b3c4)`.
+    pub fn label(&self) -> String {
+        if let Some(ref branch) = self.branch {
+            return branch.clone();
+        }
+        match (self.short_head(), &self.describe) {
+            (Some(head), Some(describe)) => format!("{} ({})", head, describe),
+            (Some(head), None) => format!("{} (detached)", head),
+            (None, _) => "(bare)".to_string(),
+        }
+    }
+}
+
+/// Fills in `describe` for the detached worktrees. Commits no tag leads to
+/// are left without one.
+pub fn describe_detached(repo: &Repository, worktrees: &mut [Worktree]) {
+    for worktree in worktrees.iter_mut().filter(|worktree| worktree.detached) {
+        worktree.describe = worktree.head.as_deref().and_then(|head| {
+            let object = repo.find_object(Oid::from_str(head).ok()?, None).ok()?;
+            let describe = object
+                .describe(DescribeOptions::new().describe_tags())
+                .ok()?;
+            describe.format(None).ok()
+        });
+    }
+}
+
+/// Whether `head` is on a branch, remote-tracking branch or tag, so removing
+/// a worktree detached at it can't lose any commits.
+pub fn head_is_referenced(repo: &Repository, head: &str) -> Result<bool> {
+    let head = Oid::from_str(head)?;
+    for reference in repo.references()? {
+        let Ok(commit) = reference?.peel_to_commit() else {
+            continue;
+        };
+        if commit.id() == head || repo.graph_descendant_of(commit.id(), head)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Parses the output of `git worktree list --porcelain`.
+///
+/// Each worktree is a block of `<label> [value]` lines separated by a blank
+/// line. Unknown labels are ignored so newer git versions keep working.
+pub fn parse_porcelain(output: &str) -> Vec<Worktree> {
+    let mut worktrees = Vec::new();
+    let mut current: Option<Worktree> = None;
+
+    for line in output.lines() {
+        let (label, value) = line.split_once(' ').unwrap_or((line, ""));
+
+        match label {
+            "worktree" => {
+                worktrees.extend(current.take());
+                current = Some(Worktree {
+                    path: PathBuf::from(value),
+                    ..Default::default()
+                });
+            }
+            "" => worktrees.extend(current.take()),
+            _ => {
+                let Some(ref mut worktree) = current else {
+                    continue;
+                };
+
+                match label {
+                    "HEAD" => worktree.head = Some(value.to_string()),
+                    "branch" => {
+                        let branch = value.strip_prefix("refs/heads/").unwrap_or(value);
+                        worktree.branch = Some(branch.to_string());
+                    }
+                    "bare" => worktree.bare = true,
+                    "detached" => worktree.detached = true,
+                    "locked" => worktree.locked = true,
+                    "prunable" => worktree.prunable = true,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    worktrees.extend(current);
+    worktrees
+}
+
+/// Local state of a worktree that would be lost by removing it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct WorktreeChanges {
+    pub modified: usize,
+    pub staged: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+    /// Stashes made on the worktree's branch. These survive removal since
+    /// stashes are shared by all worktrees, but are easy to forget about.
+    pub stashes: usize,
+}
+
+impl WorktreeChanges {
+    pub fn of_worktree(path: &Path, branch: Option<&str>) -> Result<Self> {
+        let mut repo = Repository::open(path)?;
+        let mut changes = Self::default();
+
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+
+        for entry in repo.statuses(Some(&mut options))?.iter() {
+            let status = entry.status();
+
+            if status.is_conflicted() {
+                changes.conflicted += 1;
+                continue;
+            }
+            if status.is_wt_new() {
+                changes.untracked += 1;
+                continue;
+            }
+            if status.intersects(
+                Status::INDEX_NEW
+                    | Status::INDEX_MODIFIED
+                    | Status::INDEX_DELETED
+                    | Status::INDEX_RENAMED
+                    | Status::INDEX_TYPECHANGE,
+            ) {
+                changes.staged += 1;
+            }
+            if status.intersects(
+                Status::WT_MODIFIED
+                    | Status::WT_DELETED
+                    | Status::WT_RENAMED
+                    | Status::WT_TYPECHANGE,
+            ) {
+                changes.modified += 1;
+            }
+        }
+
+        if let Some(branch) = branch {
+            // git records the branch in the stash message: "WIP on <branch>: ..."
+            // for plain `git stash`, "On <branch>: ..." when a message was given
+            let prefixes = [format!("WIP on {}:", branch), format!("On {}:", branch)];
+            repo.stash_foreach(|_, message, _| {
+                if prefixes.iter().any(|prefix| message.starts_with(prefix)) {
+                    changes.stashes += 1;
+                }
+                true
+            })?;
+        }
+
+        Ok(changes)
+    }
+
+    /// Whether `git worktree remove` would refuse to run without `--force`.
+    pub fn blocks_removal(&self) -> bool {
+        self.modified + self.staged + self.untracked + self.conflicted > 0
+    }
+}
+
+/// Summarizes the uncommitted changes; stashes are left out since removing
+/// the worktree doesn't touch them.
+impl fmt::Display for WorktreeChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = [
+            (self.modified, "modified"),
+            (self.staged, "staged"),
+            (self.untracked, "untracked"),
+            (self.conflicted, "conflicted"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{} {}", count, label))
+        .collect();
+
+        if parts.is_empty() {
+            write!(f, "clean")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_porcelain() {
+        let output = "\
+worktree /repo
+HEAD 1111111111111111111111111111111111111111
+branch refs/heads/main
+
+worktree /wt/feature
+HEAD 2222222222222222222222222222222222222222
+branch refs/heads/feature/login
+locked reason given by git
+some-future-label value
+
+worktree /wt/v1.0
+HEAD 3333333333333333333333333333333333333333
+detached
+prunable gitdir file points to non-existent location
+";
+        let worktrees = parse_porcelain(output);
+
+        assert_eq!(worktrees.len(), 3);
+        let [main, feature, detached] = &worktrees[..] else {
+            unreachable!();
+        };
+        assert_eq!(main.path, Path::new("/repo"));
+        assert_eq!(main.branch.as_deref(), Some("main"));
+        assert!(!main.locked && !main.detached && !main.prunable);
+
+        assert_eq!(feature.branch.as_deref(), Some("feature/login"));
+        assert_eq!(
+            feature.head.as_deref(),
+            Some("2222222222222222222222222222222222222222")
+        );
+        assert!(feature.locked);
+
+        assert_eq!(detached.path, Path::new("/wt/v1.0"));
+        assert_eq!(detached.branch, None);
+        assert!(detached.detached && detached.prunable);
+        assert_eq!(detached.name(), "v1.0");
+    }
+
+    #[test]
+    fn parses_bare_repository_without_trailing_blank_line() {
+        let worktrees = parse_porcelain("worktree /repo.git\nbare");
+
+        assert_eq!(worktrees.len(), 1);
+        assert!(worktrees[0].bare);
+        assert_eq!(worktrees[0].head, None);
+    }
+
+    #[test]
+    fn parses_nothing() {
+        assert!(parse_porcelain("").is_empty());
+    }
+}
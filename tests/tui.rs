@@ -0,0 +1,620 @@
+//! Drives the TUI without a terminal: scripted keys go through `run_app`
+//! into a `TestBackend`, in throwaway repositories whose `origin` is a bare
+//! repository next to them. Each screen's last frame is kept as a snapshot.
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use git2::{
+    BranchType, Oid, Repository, RepositoryInitOptions, Signature, Time, build::CheckoutBuilder,
+};
+use gitsy::{App, EventSource, GitsyConfig, Screen, run_app};
+use insta::assert_snapshot;
+use ratatui::{Terminal, backend::TestBackend};
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tempfile::TempDir;
+
+const WIDTH: u16 = 120;
+const HEIGHT: u16 = 30;
+
+/// How long `Script::settle` waits before failing the test, far more than
+/// a fetch from a local repository takes.
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+enum Step {
+    Event(Event),
+    /// Until the background work finishes or the screen changes
+    Settle {
+        screen: Option<Screen>,
+        started: Option<Instant>,
+    },
+}
+
+/// Keys to press one after another. Once they run out, Ctrl+C ends the app.
+struct Script {
+    steps: VecDeque<Step>,
+    screen: Screen,
+    busy: bool,
+}
+
+impl Script {
+    fn new() -> Self {
+        Self {
+            steps: VecDeque::new(),
+            screen: Screen::Dashboard,
+            busy: false,
+        }
+    }
+
+    fn key(mut self, code: KeyCode) -> Self {
+        self.steps
+            .push_back(Step::Event(Event::Key(KeyEvent::from(code))));
+        self
+    }
+
+    /// Types `text` a character at a time.
+    fn keys(self, text: &str) -> Self {
+        text.chars()
+            .fold(self, |script, c| script.key(KeyCode::Char(c)))
+    }
+
+    fn enter(self) -> Self {
+        self.key(KeyCode::Enter)
+    }
+
+    /// Lets a fetch, removal or hooks on a background thread finish, since
+    /// keys are ignored while they run. Also done once the screen changes,
+    /// which the last of them often does.
+    fn settle(mut self) -> Self {
+        self.steps.push_back(Step::Settle {
+            screen: None,
+            started: None,
+        });
+        self
+    }
+}
+
+impl EventSource for Script {
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        match self.steps.front_mut() {
+            Some(Step::Event(event)) => {
+                let event = event.clone();
+                self.steps.pop_front();
+                Ok(Some(event))
+            }
+            Some(Step::Settle { screen, started }) => {
+                let screen = *screen.get_or_insert(self.screen);
+                let started = *started.get_or_insert_with(Instant::now);
+                if !self.busy || self.screen != screen {
+                    self.steps.pop_front();
+                } else {
+                    assert!(
+                        started.elapsed() < SETTLE_TIMEOUT,
+                        "still busy on {:?} after {:?}",
+                        screen,
+                        SETTLE_TIMEOUT
+                    );
+                    thread::sleep(timeout.min(Duration::from_millis(10)));
+                }
+                Ok(None)
+            }
+            None => Ok(Some(Event::Key(KeyEvent::new(
+                KeyCode::Char('c'),
+                KeyModifiers::CONTROL,
+            )))),
+        }
+    }
+
+    fn observe(&mut self, screen: Screen, busy: bool) {
+        self.screen = screen;
+        self.busy = busy;
+    }
+}
+
+/// A repository with one commit on `main`, pushed to a bare `origin`, that
+/// keeps its worktrees in `wt` next to it.
+struct Fixture {
+    /// Removes everything once the test is done
+    _dir: TempDir,
+    root: PathBuf,
+    repo: Repository,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let remote_path = root.join("origin.git");
+        Repository::init_opts(
+            &remote_path,
+            RepositoryInitOptions::new().bare(true).initial_head("main"),
+        )
+        .unwrap();
+        let repo = Repository::init_opts(
+            root.join("repo"),
+            RepositoryInitOptions::new().initial_head("main"),
+        )
+        .unwrap();
+        fs::create_dir(root.join("wt")).unwrap();
+
+        let fixture = Self {
+            _dir: dir,
+            root,
+            repo,
+        };
+        fixture.commit("README.md", "# Demo\n", "Initial commit");
+
+        fixture.push_main(&remote_path);
+        fixture
+    }
+
+    /// Pushes `main` to a new `origin` at `remote_path` and tracks it.
+    fn push_main(&self, remote_path: &Path) {
+        let mut origin = self
+            .repo
+            .remote("origin", remote_path.to_str().unwrap())
+            .unwrap();
+        origin
+            .push(&["refs/heads/main:refs/heads/main"], None)
+            .unwrap();
+        origin.fetch::<&str>(&[], None, None).unwrap();
+        self.repo
+            .find_branch("main", BranchType::Local)
+            .unwrap()
+            .set_upstream(Some("origin/main"))
+            .unwrap();
+    }
+
+    fn repo_root(&self) -> PathBuf {
+        self.root.join("repo")
+    }
+
+    /// Commits `content` as `file` on the current branch.
+    fn commit(&self, file: &str, content: &str, message: &str) -> Oid {
+        commit(&self.repo, file, content, message)
+    }
+
+    /// Adds a worktree in `wt` for a new branch at `main`.
+    fn worktree(&self, branch: &str) -> PathBuf {
+        let commit = self.repo.head().unwrap().peel_to_commit().unwrap();
+        let branch_ref = self.repo.branch(branch, &commit, false).unwrap();
+        let path = self.root.join("wt").join(branch);
+
+        let mut options = git2::WorktreeAddOptions::new();
+        options.reference(Some(branch_ref.get()));
+        self.repo.worktree(branch, &path, Some(&options)).unwrap();
+        path
+    }
+
+    /// Adds a worktree for a new branch with a commit of its own, merged
+    /// back into `main` as a pull request would be.
+    fn merged_worktree(&self, branch: &str) -> PathBuf {
+        let path = self.worktree(branch);
+        let worktree = Repository::open(&path).unwrap();
+        let tip = commit(
+            &worktree,
+            &format!("{}.txt", branch),
+            "done\n",
+            &format!("Work on {}", branch),
+        );
+
+        let main = self.repo.head().unwrap().peel_to_commit().unwrap();
+        let tip = self.repo.find_commit(tip).unwrap();
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature(),
+                &signature(),
+                &format!("Merge branch '{}'", branch),
+                &tip.tree().unwrap(),
+                &[&main, &tip],
+            )
+            .unwrap();
+        self.repo
+            .checkout_head(Some(CheckoutBuilder::new().force()))
+            .unwrap();
+        path
+    }
+
+    /// Adds a worktree for a new branch with a commit of its own, pushed and
+    /// tracked, whose remote branch has since been deleted and pruned
+    /// without it being merged.
+    fn gone_worktree(&self, branch: &str) -> PathBuf {
+        let path = self.worktree(branch);
+        let worktree = Repository::open(&path).unwrap();
+        commit(
+            &worktree,
+            &format!("{}.txt", branch),
+            "unfinished\n",
+            &format!("Start on {}", branch),
+        );
+
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+        let mut origin = self.repo.find_remote("origin").unwrap();
+        origin.push(&[refspec.as_str()], None).unwrap();
+        origin.fetch::<&str>(&[], None, None).unwrap();
+        self.repo
+            .find_branch(branch, BranchType::Local)
+            .unwrap()
+            .set_upstream(Some(&format!("origin/{}", branch)))
+            .unwrap();
+        self.repo
+            .find_reference(&format!("refs/remotes/origin/{}", branch))
+            .unwrap()
+            .delete()
+            .unwrap();
+        path
+    }
+
+    fn app(&self) -> App {
+        let config: GitsyConfig = toml::from_str(
+            r#"
+            worktree_path = "../wt"
+            default_base_branch = "main"
+            "#,
+        )
+        .unwrap();
+        App::new(self.repo_root(), config)
+    }
+
+    /// Runs `script` against `app` and returns the last frame, with the
+    /// temporary directory replaced so snapshots don't change between runs.
+    fn run(&self, app: &mut App, script: Script) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+        let mut script = script;
+        run_app(&mut terminal, app, &mut script).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let lines: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                let line: String = (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect();
+                line.trim_end().to_string()
+            })
+            .collect();
+        lines
+            .join("\n")
+            .replace(&self.root.display().to_string(), "[tmp]")
+    }
+}
+
+/// Commits `content` as `file` on the current branch of `repo`.
+fn commit(repo: &Repository, file: &str, content: &str, message: &str) -> Oid {
+    fs::write(repo.workdir().unwrap().join(file), content).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(file)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+
+    repo.commit(
+        Some("HEAD"),
+        &signature(),
+        &signature(),
+        message,
+        &tree,
+        parent.as_ref().into_iter().collect::<Vec<_>>().as_slice(),
+    )
+    .unwrap()
+}
+
+/// Commits are dated two days ago so their age reads the same whenever the
+/// tests run.
+fn signature() -> Signature<'static> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let time = Time::new(now.as_secs() as i64 - 2 * 24 * 60 * 60, 0);
+    Signature::new("Gitsy", "gitsy@example.com", &time).unwrap()
+}
+
+#[test]
+fn dashboard() {
+    let fixture = Fixture::new();
+    fixture.commit("CHANGELOG.md", "# Changes\n", "Start a changelog");
+    let feature = fixture.worktree("feature");
+    fs::write(feature.join("notes.txt"), "todo\n").unwrap();
+    fixture.merged_worktree("done");
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new());
+
+    assert_eq!(app.screen(), Screen::Dashboard);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn dashboard_filtered() {
+    let fixture = Fixture::new();
+    fixture.worktree("feature");
+    fixture.worktree("bugfix");
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("/bug"));
+
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn dashboard_fetch_refreshes_ahead_behind() {
+    let fixture = Fixture::new();
+    // Someone else pushes to main
+    let remote = Repository::open(fixture.root.join("origin.git")).unwrap();
+    let main = remote
+        .find_reference("refs/heads/main")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let signature = Signature::now("Gitsy", "gitsy@example.com").unwrap();
+    remote
+        .commit(
+            Some("refs/heads/main"),
+            &signature,
+            &signature,
+            "Pushed elsewhere",
+            &main.tree().unwrap(),
+            &[&main],
+        )
+        .unwrap();
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("f").settle());
+
+    assert_eq!(app.screen(), Screen::Dashboard);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn dashboard_refuses_to_delete_main_worktree() {
+    let fixture = Fixture::new();
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("d"));
+
+    assert_eq!(app.screen(), Screen::Dashboard);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn select_base_branch_option() {
+    let fixture = Fixture::new();
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("c"));
+
+    assert_eq!(app.screen(), Screen::SelectBaseBranchOption);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn create_branch() {
+    let fixture = Fixture::new();
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("c").enter().keys("login"));
+
+    assert_eq!(app.screen(), Screen::CreateBranch);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn create_branch_warns_about_existing_branch() {
+    let fixture = Fixture::new();
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("c").enter().keys("main"));
+
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn create_branch_adds_worktree() {
+    let fixture = Fixture::new();
+    let mut app = fixture.app();
+
+    let frame = fixture.run(
+        &mut app,
+        Script::new().keys("c").enter().keys("login").enter(),
+    );
+
+    let worktree = fixture.root.join("wt").join("login");
+    assert!(worktree.join("README.md").is_file());
+    let branch = fixture
+        .repo
+        .find_branch("login", BranchType::Local)
+        .unwrap();
+    assert_eq!(
+        branch.get().target(),
+        fixture.repo.refname_to_id("refs/heads/main").ok()
+    );
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn create_branch_shows_up_on_dashboard() {
+    let fixture = Fixture::new();
+    let mut app = fixture.app();
+
+    let frame = fixture.run(
+        &mut app,
+        Script::new()
+            .keys("c")
+            .enter()
+            .keys("login")
+            .enter()
+            .key(KeyCode::Esc),
+    );
+
+    assert_eq!(app.screen(), Screen::Dashboard);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn select_remote() {
+    let fixture = Fixture::new();
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("c").key(KeyCode::Down).enter());
+
+    assert_eq!(app.screen(), Screen::SelectRemote);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn select_remote_branch_after_fetch() {
+    let fixture = Fixture::new();
+    // A branch that only exists on the remote until it is fetched
+    let remote = Repository::open(fixture.root.join("origin.git")).unwrap();
+    let main = remote.find_reference("refs/heads/main").unwrap();
+    remote
+        .reference(
+            "refs/heads/release",
+            main.target().unwrap(),
+            false,
+            "release branch",
+        )
+        .unwrap();
+    let mut app = fixture.app();
+
+    let frame = fixture.run(
+        &mut app,
+        Script::new()
+            .keys("c")
+            .key(KeyCode::Down)
+            .enter()
+            .enter()
+            .settle(),
+    );
+
+    assert_eq!(app.screen(), Screen::SelectRemoteBranch);
+    assert!(
+        fixture
+            .repo
+            .find_branch("origin/release", BranchType::Remote)
+            .is_ok()
+    );
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn confirm_delete() {
+    let fixture = Fixture::new();
+    fixture.worktree("feature");
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("jd"));
+
+    assert_eq!(app.screen(), Screen::ConfirmDelete);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn confirm_delete_removes_worktree() {
+    let fixture = Fixture::new();
+    let path = fixture.worktree("feature");
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("jdy").settle());
+
+    assert_eq!(app.screen(), Screen::Dashboard);
+    assert!(!path.exists());
+    // Only the worktree goes by default
+    assert!(
+        fixture
+            .repo
+            .find_branch("feature", BranchType::Local)
+            .is_ok()
+    );
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn confirm_delete_needs_force_for_unmerged_branch() {
+    let fixture = Fixture::new();
+    let path = fixture.worktree("feature");
+    commit(
+        &Repository::open(&path).unwrap(),
+        "feature.txt",
+        "unfinished\n",
+        "Start on feature",
+    );
+    let mut app = fixture.app();
+
+    // Tab asks for the local branch to go too, and y no longer does it
+    let frame = fixture.run(
+        &mut app,
+        Script::new().keys("jd").key(KeyCode::Tab).keys("y"),
+    );
+
+    assert_eq!(app.screen(), Screen::ConfirmDelete);
+    assert!(path.exists());
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn cleanup() {
+    let fixture = Fixture::new();
+    fixture.merged_worktree("merged");
+    // Not offered, having nothing of its own to merge yet
+    fixture.worktree("feature");
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("x"));
+
+    assert_eq!(app.screen(), Screen::Cleanup);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn confirm_cleanup() {
+    let fixture = Fixture::new();
+    fixture.merged_worktree("merged");
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("x").enter());
+
+    assert_eq!(app.screen(), Screen::ConfirmCleanup);
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn cleanup_summary() {
+    let fixture = Fixture::new();
+    let path = fixture.merged_worktree("merged");
+    let mut app = fixture.app();
+
+    let frame = fixture.run(&mut app, Script::new().keys("x").enter().keys("y").settle());
+
+    assert_eq!(app.screen(), Screen::CleanupSummary);
+    assert!(!path.exists());
+    assert_snapshot!(frame);
+}
+
+#[test]
+fn cleanup_keeps_unmerged_branches() {
+    let fixture = Fixture::new();
+    let merged = fixture.merged_worktree("merged");
+    let gone = fixture.gone_worktree("gone");
+    let mut app = fixture.app();
+
+    // Tab asks for the local branches to go too
+    let frame = fixture.run(
+        &mut app,
+        Script::new()
+            .keys("x")
+            .enter()
+            .key(KeyCode::Tab)
+            .keys("y")
+            .settle(),
+    );
+
+    assert_eq!(app.screen(), Screen::CleanupSummary);
+    assert!(!merged.exists() && !gone.exists());
+    let repo = &fixture.repo;
+    assert!(repo.find_branch("merged", BranchType::Local).is_err());
+    assert!(repo.find_branch("gone", BranchType::Local).is_ok());
+    assert_snapshot!(frame);
+}